@@ -2,7 +2,11 @@ use log::info;
 use tokio::sync::mpsc::UnboundedReceiver;
 use udf_solana::data::MultipleUpdateMessage;
 
-use crate::{config::PublisherConfig, data::LatestUpdate, data_feed_processor::DataFeedProcessor};
+use crate::{
+    config::PublisherConfig,
+    data::{DeadLetterInfo, LatestUpdate},
+    data_feed_processor::DataFeedProcessor,
+};
 
 pub(crate) struct PublisherApp {
     data_feed_proc: DataFeedProcessor,
@@ -28,7 +32,22 @@ impl PublisherApp {
         self.data_feed_proc.get_latest_update(data_key).await.unwrap_or_default()
     }
 
+    pub(crate) async fn get_latest_updates(
+        &self,
+        data_keys: &[[u8; 32]],
+    ) -> Result<Vec<Option<LatestUpdate>>, ()> {
+        self.data_feed_proc.get_latest_updates(data_keys).await
+    }
+
     pub(crate) fn get_chain_id(&self) -> u128 {
         self.data_feed_proc.get_chain_id()
     }
+
+    pub(crate) fn dead_letters(&self) -> Vec<DeadLetterInfo> {
+        self.data_feed_proc.dead_letters().infos()
+    }
+
+    pub(crate) fn clear_dead_letters(&self) {
+        self.data_feed_proc.dead_letters().clear()
+    }
 }
@@ -1,16 +1,48 @@
 use config::{Config, File};
 use log::{error, info};
-use serde::Deserialize;
-use solana_sdk::signature::Keypair;
+use serde::{Deserialize, Deserializer};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
 use solana_tools::{solana_logs::SolanaClientConfig, utils::deserialize_keypair};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
 
-use crate::error::PublisherError;
+use crate::{
+    error::PublisherError, priority_fee::PriorityFeeConfig, retry::RetryConfig,
+    transaction_planner::ComputeUnitsConfig,
+};
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct PublisherConfig {
     pub(crate) solana: SolanaClientConfig,
     #[serde(deserialize_with = "deserialize_keypair")]
     pub(crate) publisher: Keypair,
+    /// The address lookup table of the update transactions, the one persisted at
+    /// `lookup_table_path` is used or a new one is created when it's not set
+    #[serde(default, deserialize_with = "deserialize_optional_pubkey")]
+    pub(crate) lookup_table: Option<Pubkey>,
+    /// The file the address of the lookup table created by the publisher is persisted in
+    #[serde(default = "default_lookup_table_path")]
+    pub(crate) lookup_table_path: PathBuf,
+    /// How often the cached price oracle config and protocol info are refetched
+    #[serde(default = "default_oracle_state_refresh_interval_sec")]
+    pub(crate) oracle_state_refresh_interval_sec: u64,
+    /// How many transactions of a single update message are submitted at once
+    #[serde(default = "default_max_parallel_transactions")]
+    pub(crate) max_parallel_transactions: usize,
+    #[serde(default)]
+    pub(crate) retry: RetryConfig,
+    /// How many dead letters are kept for the host to inspect
+    #[serde(default = "default_dead_letter_capacity")]
+    pub(crate) dead_letter_capacity: usize,
+    #[serde(default)]
+    pub(crate) priority_fee: PriorityFeeConfig,
+    #[serde(default)]
+    pub(crate) compute_units: ComputeUnitsConfig,
+    /// The address the prometheus metrics are served at, disabled when not set
+    #[serde(default)]
+    pub(crate) metrics_address: Option<SocketAddr>,
+    /// How often the publisher balance is refetched for the metrics
+    #[serde(default = "default_balance_refresh_interval_sec")]
+    pub(crate) balance_refresh_interval_sec: u64,
 }
 
 impl PublisherConfig {
@@ -31,3 +63,32 @@ impl PublisherConfig {
         })
     }
 }
+
+fn default_lookup_table_path() -> PathBuf {
+    PathBuf::from("solana_lookup_table")
+}
+
+fn default_oracle_state_refresh_interval_sec() -> u64 {
+    60
+}
+
+fn default_balance_refresh_interval_sec() -> u64 {
+    30
+}
+
+fn default_max_parallel_transactions() -> usize {
+    4
+}
+
+fn default_dead_letter_capacity() -> usize {
+    100
+}
+
+fn deserialize_optional_pubkey<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|key| Pubkey::from_str(&key).map_err(serde::de::Error::custom))
+        .transpose()
+}
@@ -1,6 +1,8 @@
 use std::slice;
 use udf_solana::data::{DataFeed, MultipleUpdateMessage, TransmitterSignature};
 
+use crate::dead_letter::DeadLetter;
+
 #[derive(Clone, Debug)]
 #[repr(C)]
 pub(crate) struct MerkleRootUpdateMultiple {
@@ -26,6 +28,54 @@ pub(crate) struct LatestUpdate {
     pub(crate) timestamp: u64,
 }
 
+/// The dead letter as it is exposed to the host, `failure_kind` is 1 for rpc, 2 for program errors
+/// and 3 for the data feeds too large for a transaction, `failed_at` is the unix timestamp of the
+/// last attempt
+#[derive(Clone, Debug, Default)]
+#[repr(C)]
+pub(crate) struct DeadLetterInfo {
+    pub(crate) merkle_root: [u8; 32],
+    pub(crate) data_feeds_len: usize,
+    pub(crate) attempts: u32,
+    pub(crate) failure_kind: u32,
+    pub(crate) failed_at: i64,
+}
+
+impl From<&DeadLetter> for DeadLetterInfo {
+    fn from(value: &DeadLetter) -> Self {
+        DeadLetterInfo {
+            merkle_root: value.msg.merkle_root,
+            data_feeds_len: value.msg.data_feeds.len(),
+            attempts: value.attempts,
+            failure_kind: value.kind as u32,
+            failed_at: value.failed_at,
+        }
+    }
+}
+
+/// The latest update of a data key as it is read in batch, `found` is false for the data keys
+/// that have never been published
+#[derive(Clone, Debug, Default)]
+#[repr(C)]
+pub(crate) struct LatestUpdateResult {
+    pub(crate) price: [u8; 32],
+    pub(crate) timestamp: u64,
+    pub(crate) found: bool,
+}
+
+impl From<Option<LatestUpdate>> for LatestUpdateResult {
+    fn from(value: Option<LatestUpdate>) -> Self {
+        match value {
+            Some(latest_update) => LatestUpdateResult {
+                price: latest_update.price,
+                timestamp: latest_update.timestamp,
+                found: true,
+            },
+            None => LatestUpdateResult::default(),
+        }
+    }
+}
+
 impl From<&EcdsaSignature> for TransmitterSignature {
     fn from(value: &EcdsaSignature) -> Self {
         TransmitterSignature {
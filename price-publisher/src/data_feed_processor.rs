@@ -1,34 +1,59 @@
-use anchor_client::{Client, Cluster};
-use anchor_lang::{
-    prelude::{AccountMeta, Pubkey},
-    InstructionData, ToAccountMetas,
-};
-use log::{debug, error};
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures::{stream, StreamExt};
+use log::{debug, error, info, warn};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
 use solana_sdk::{
-    instruction::Instruction,
-    signature::{Keypair, Signer},
+    address_lookup_table::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    signature::{Signature, Signer},
 };
 use solana_tools::solana_transactor::{ix_compiler::InstructionBundle, RpcPool, SolanaTransactor};
-use tokio::sync::{mpsc::UnboundedReceiver, Mutex};
-use udf_solana::{
-    data::{
-        Config as UdfConfig, DataFeed, LatestUpdate as LatestUpdateAccount, MultipleUpdateMessage,
-    },
-    udf_solana::ROOT,
+use solana_transaction_status::{
+    UiReturnDataEncoding, UiTransactionEncoding, UiTransactionReturnData,
+};
+use std::{
+    future,
+    time::{Duration, Instant},
+};
+use tokio::{
+    select,
+    sync::{mpsc::UnboundedReceiver, Mutex},
+};
+use udf_solana_client::{
+    accounts, instructions, pda, DataFeed, DataKey, MultipleUpdateMessage, MultipleUpdateMessageV2,
+    UpdateStatus,
 };
 
-use crate::{config::PublisherConfig, data::LatestUpdate};
+use crate::{
+    config::PublisherConfig,
+    data::LatestUpdate,
+    dead_letter::{DeadLetter, DeadLetterQueue},
+    known_timestamps::KnownTimestamps,
+    lookup_table::LookupTableManager,
+    metrics::{self, METRICS},
+    oracle_state::OracleStateCache,
+    priority_fee::PriorityFeeStrategy,
+    retry::FailureKind,
+    status_callback,
+    transaction_planner::{self, PlannedChunk, UpdateMessage},
+};
 
-const PHOTON_ROOT: &[u8] = b"r0";
-const PHOTON_PROGRAM: Pubkey = Pubkey::new_from_array([
-    12, 50, 145, 223, 16, 33, 233, 37, 119, 186, 206, 30, 187, 117, 189, 70, 23, 0, 141, 139, 21,
-    92, 169, 187, 124, 139, 89, 86, 127, 197, 95, 163,
-]);
-const DATA_FEED_CHUNK_SIZE: usize = 3;
+/// The outcome of submitting a single chunk of the update message
+struct ChunkOutcome {
+    data_feeds: usize,
+    result: Result<Vec<Signature>, String>,
+}
 
 pub(crate) struct DataFeedProcessor {
     data_feed_msg_receiver: Mutex<UnboundedReceiver<MultipleUpdateMessage>>,
     transactor: SolanaTransactor,
+    rpc_client: RpcClient,
+    lookup_table: LookupTableManager,
+    oracle_state: OracleStateCache,
+    dead_letters: DeadLetterQueue,
+    known_timestamps: KnownTimestamps,
+    priority_fee: PriorityFeeStrategy,
     publisher_config: PublisherConfig,
 }
 
@@ -43,163 +68,422 @@ impl DataFeedProcessor {
         let transactor = SolanaTransactor::start(rpc_pool)
             .await
             .map_err(|err| error!("Failed to start solana transactor: {}", err))?;
+        let read_rpc = publisher_config
+            .solana
+            .read_rpcs
+            .first()
+            .ok_or_else(|| error!("Failed to get first read rpc"))?;
+        let rpc_client =
+            RpcClient::new_with_commitment(read_rpc.url.to_string(), CommitmentConfig::confirmed());
+        let lookup_table = LookupTableManager::try_new(
+            &rpc_client,
+            &transactor,
+            &publisher_config.publisher,
+            publisher_config.lookup_table,
+            &publisher_config.lookup_table_path,
+        )
+        .await?;
+        let oracle_state = OracleStateCache::try_new(
+            &rpc_client,
+            Duration::from_secs(publisher_config.oracle_state_refresh_interval_sec),
+        )
+        .await?;
         Ok(DataFeedProcessor {
             data_feed_msg_receiver: Mutex::new(data_feed_msg_receiver),
             transactor,
+            rpc_client,
+            lookup_table,
+            oracle_state,
+            dead_letters: DeadLetterQueue::new(publisher_config.dead_letter_capacity),
+            known_timestamps: KnownTimestamps::default(),
+            priority_fee: PriorityFeeStrategy::new(publisher_config.priority_fee.clone()),
             publisher_config,
         })
     }
 
     pub(crate) async fn execute(&self) {
+        select! {
+            _ = self.process_data_feed_msgs() => {},
+            _ = self.oracle_state.refresh(&self.rpc_client) => {},
+            _ = self.serve_metrics() => {},
+        }
+    }
+
+    async fn serve_metrics(&self) {
+        let Some(address) = self.publisher_config.metrics_address else {
+            return future::pending().await;
+        };
+        let interval = Duration::from_secs(self.publisher_config.balance_refresh_interval_sec);
+        let publisher = self.publisher_config.publisher.pubkey();
+        select! {
+            _ = metrics::serve(address) => {},
+            _ = metrics::watch_balance(&self.rpc_client, &publisher, interval) => {},
+        }
+    }
+
+    async fn process_data_feed_msgs(&self) {
         while let Some(data_feed_msg) = self.data_feed_msg_receiver.lock().await.recv().await {
+            METRICS.messages_received.inc();
             let _ = self.process_data_feed_msg(data_feed_msg).await;
         }
     }
 
     async fn process_data_feed_msg(&self, data_feed_msg: MultipleUpdateMessage) -> Result<(), ()> {
         debug!("data_feed_msg: {}", data_feed_msg);
-        let read_rpc = self
-            .publisher_config
-            .solana
-            .read_rpcs
-            .first()
-            .ok_or_else(|| error!("Failed to get first read rpc"))?;
         let publisher = &self.publisher_config.publisher;
-        let client =
-            Client::new(Cluster::Custom(read_rpc.url.to_string(), String::default()), publisher);
-        let program = client
-            .program(udf_solana::ID)
-            .map_err(|err| error!("Failed to get udf_solana program client: {}", err))?;
-        let (config, _) = Pubkey::find_program_address(&[ROOT, b"CONFIG"], &udf_solana::ID);
-        let config_acc: UdfConfig = program.account(config).await.map_err(|err| {
-            error!(
-                "Failed to get price oracle sc config account data at: {}, error: {}",
-                config, err
-            )
+        let oracle_state = self.oracle_state.state().await;
+        let config = &oracle_state.config;
+        debug!(
+            "Signatures: {}, transmitters: {}, protocol info: {}",
+            data_feed_msg.signatures.len(),
+            oracle_state.transmitters.len(),
+            oracle_state.protocol_info
+        );
+        let received_feeds = data_feed_msg.data_feeds.len();
+        let data_feed_msg = self
+            .known_timestamps
+            .filter(&self.rpc_client, &config.protocol_id, data_feed_msg)
+            .await;
+        METRICS.feeds_ignored.inc_by((received_feeds - data_feed_msg.data_feeds.len()) as u64);
+        if data_feed_msg.data_feeds.is_empty() {
+            info!(
+                "Merkle root: {}, no data feeds newer than on chain, skipped",
+                hex::encode(data_feed_msg.merkle_root)
+            );
+            return Ok(());
+        }
+        let base_accounts = instructions::update_assets_accounts(
+            &publisher.pubkey(),
+            &config.protocol_id,
+            &config.endpoint,
+        );
+        let data_feed_msg = MultipleUpdateMessageV2::try_from(data_feed_msg).map_err(|err| {
+            METRICS.failures.with_label_values(&["signatures"]).inc();
+            error!("Failed to parse data feed message signatures: {}", err)
         })?;
+        let lookup_table = self.lookup_table.lookup_table().await;
+        let mut lookup_table_addresses: Vec<Pubkey> =
+            base_accounts.iter().filter(|meta| !meta.is_signer).map(|meta| meta.pubkey).collect();
 
-        let (protocol_info, _) = Pubkey::find_program_address(
-            &[PHOTON_ROOT, b"PROTOCOL", &config_acc.protocol_id],
-            &PHOTON_PROGRAM,
+        let plan = transaction_planner::plan_chunks(
+            &publisher.pubkey(),
+            &lookup_table,
+            &self.publisher_config.compute_units,
+            &data_feed_msg,
+            |msg| {
+                let mut ix = match msg {
+                    UpdateMessage::Proofs(msg) => instructions::update_multiple_assets_v2(
+                        &publisher.pubkey(),
+                        &config.protocol_id,
+                        &config.endpoint,
+                        msg,
+                    ),
+                    UpdateMessage::MultiProof(msg) => {
+                        instructions::update_multiple_assets_multiproof(
+                            &publisher.pubkey(),
+                            &config.protocol_id,
+                            &config.endpoint,
+                            msg,
+                        )
+                    }
+                };
+                ix.accounts
+                    .extend(instructions::reimbursement_accounts(config, &publisher.pubkey()));
+                ix
+            },
         );
-        let base_accounts: Vec<AccountMeta> = udf_solana::accounts::UpdateAssets {
-            publisher: publisher.pubkey(),
-            config,
-            protocol_info,
-            system_program: anchor_lang::system_program::ID,
-        }
-        .to_account_metas(None);
-
-        for data_feeds in data_feed_msg.data_feeds.chunks(DATA_FEED_CHUNK_SIZE) {
-            let mut msg = data_feed_msg.clone();
-            msg.data_feeds = data_feeds.to_vec();
-
-            let mut accounts = base_accounts.clone();
-            Self::extend_accounts_by_feeds(&msg.data_feeds, &config_acc.protocol_id, &mut accounts);
-            let update_mult_assets_data = udf_solana::instruction::UpdateMultipleAssets {
-                multiple_update_message: msg,
-            }
-            .data();
-            let ix =
-                Instruction::new_with_bytes(udf_solana::id(), &update_mult_assets_data, accounts);
-            let bundle = vec![InstructionBundle::new(ix, 400000)];
-            const COMPUTE_UNIT_PRICE_LAMPORTS: u64 = 1000;
-            self.transactor
+        debug!(
+            "Data feeds: {} are planned into transactions: {}, oversized: {}",
+            data_feed_msg.data_feeds.len(),
+            plan.chunks.len(),
+            plan.oversized.len()
+        );
+
+        for chunk in &plan.chunks {
+            Self::log_data_feed_accounts(&chunk.data_feeds, &config.protocol_id);
+            lookup_table_addresses
+                .extend(chunk.ix.accounts[base_accounts.len()..].iter().map(|meta| meta.pubkey));
+        }
+
+        let mut outcomes: Vec<ChunkOutcome> = stream::iter(plan.chunks)
+            .map(|chunk| self.submit_chunk(&data_feed_msg, chunk, &lookup_table))
+            .buffer_unordered(self.publisher_config.max_parallel_transactions.max(1))
+            .collect()
+            .await;
+        if !plan.oversized.is_empty() {
+            outcomes.push(self.dead_letter_oversized(&data_feed_msg, plan.oversized));
+        }
+        let succeeded = Self::log_outcomes(&data_feed_msg.merkle_root, &outcomes);
+
+        let _ = self
+            .lookup_table
+            .extend(&self.transactor, publisher, lookup_table_addresses)
+            .await
+            .map_err(|_| warn!("Failed to extend lookup table by the data feed accounts"));
+        if succeeded {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    /// Sends the chunk retrying the failures by the retry policy, the chunk that exhausted the
+    /// attempts goes to the dead letters
+    async fn submit_chunk(
+        &self,
+        data_feed_msg: &MultipleUpdateMessageV2,
+        chunk: PlannedChunk,
+        lookup_table: &AddressLookupTableAccount,
+    ) -> ChunkOutcome {
+        let publisher = &self.publisher_config.publisher;
+        let retry = &self.publisher_config.retry;
+        let data_feeds = chunk.data_feeds;
+        let writable_accounts: Vec<Pubkey> = chunk
+            .ix
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_writable)
+            .map(|meta| meta.pubkey)
+            .collect();
+        let bundle = vec![InstructionBundle::new(chunk.ix, chunk.compute_units)];
+        let mut attempt = 0;
+        let mut failure = None;
+        let result = loop {
+            attempt += 1;
+            let compute_unit_price = self
+                .priority_fee
+                .compute_unit_price(&self.rpc_client, &writable_accounts, attempt)
+                .await;
+            let started_at = Instant::now();
+            let result = self
+                .transactor
                 .send_all_instructions::<&str>(
                     None,
                     &bundle,
                     &[publisher],
                     publisher.pubkey(),
                     1,
-                    &[],
-                    Some(COMPUTE_UNIT_PRICE_LAMPORTS),
+                    std::slice::from_ref(lookup_table),
+                    Some(compute_unit_price),
                     false,
                 )
-                .await
-                .map_err(|err| error!("Failed to process transaction: {}", err))?;
+                .await;
+            METRICS.transaction_latency.observe(started_at.elapsed().as_secs_f64());
+            let err = match result {
+                Ok(signatures) => break Ok(signatures),
+                Err(err) => err,
+            };
+            let kind = FailureKind::classify(&err);
+            let err = err.to_string();
+            METRICS.failures.with_label_values(&[kind.label()]).inc();
+            if !retry.should_retry(attempt, kind) {
+                error!("Failed to process transaction, attempt: {}, error: {}", attempt, err);
+                self.dead_letters.push(DeadLetter {
+                    msg: MultipleUpdateMessageV2 {
+                        merkle_root: data_feed_msg.merkle_root,
+                        data_feeds: data_feeds.clone(),
+                        signatures: data_feed_msg.signatures.clone(),
+                    },
+                    kind,
+                    error: err.clone(),
+                    attempts: attempt,
+                    failed_at: chrono::Utc::now().timestamp(),
+                });
+                failure = Some(kind);
+                break Err(err);
+            }
+            let backoff = retry.backoff(attempt);
+            warn!(
+                "Failed to process transaction, attempt: {}, retry in: {:?}, error: {}",
+                attempt, backoff, err
+            );
+            tokio::time::sleep(backoff).await;
+        };
+        if let Ok(signatures) = &result {
+            METRICS.feeds_submitted.inc_by(data_feeds.len() as u64);
+            for data_feed in &data_feeds {
+                self.known_timestamps.record(data_feed.data_key, data_feed.timestamp).await;
+                METRICS
+                    .last_published_timestamp
+                    .with_label_values(&[&DataKey::from(data_feed.data_key).to_string()])
+                    .set(data_feed.timestamp as i64);
+            }
+            for signature in signatures {
+                self.log_update_statuses(signature, &data_feeds).await;
+            }
+        }
+        status_callback::notify(
+            data_feed_msg.merkle_root,
+            result.as_deref().unwrap_or_default(),
+            data_feeds.len(),
+            failure,
+        );
+        ChunkOutcome {
+            data_feeds: data_feeds.len(),
+            result,
         }
-        Ok(())
     }
 
-    fn get_client(&self) -> Result<Client<&Keypair>, ()> {
-        let read_rpc = self
-            .publisher_config
-            .solana
-            .read_rpcs
-            .first()
-            .ok_or_else(|| error!("Failed to get first read rpc"))?;
-        Ok(Client::new(
-            Cluster::Custom(read_rpc.url.to_string(), String::default()),
-            &self.publisher_config.publisher,
-        ))
-    }
-
-    fn extend_accounts_by_feeds(
-        data_feeds: &[DataFeed],
-        protocol_id: &[u8],
-        accounts: &mut Vec<AccountMeta>,
-    ) {
-        let remaining_accounts = data_feeds
-            .iter()
-            .map(|data_feed| Self::get_data_key_pda(&data_feed.data_key, protocol_id));
+    /// Sends the data feeds that don't fit into a transaction straight to the dead letters, they are
+    /// reported to the host the same as a failed chunk
+    fn dead_letter_oversized(
+        &self,
+        data_feed_msg: &MultipleUpdateMessageV2,
+        data_feeds: Vec<DataFeed>,
+    ) -> ChunkOutcome {
+        let kind = FailureKind::Oversized;
+        let error = "Data feeds do not fit into a transaction".to_string();
+        METRICS.failures.with_label_values(&[kind.label()]).inc();
+        let data_feeds_len = data_feeds.len();
+        self.dead_letters.push(DeadLetter {
+            msg: MultipleUpdateMessageV2 {
+                merkle_root: data_feed_msg.merkle_root,
+                data_feeds,
+                signatures: data_feed_msg.signatures.clone(),
+            },
+            kind,
+            error: error.clone(),
+            attempts: 0,
+            failed_at: chrono::Utc::now().timestamp(),
+        });
+        status_callback::notify(data_feed_msg.merkle_root, &[], data_feeds_len, Some(kind));
+        ChunkOutcome {
+            data_feeds: data_feeds_len,
+            result: Err(error),
+        }
+    }
+
+    /// Logs the summary of the whole update message, returns whether every chunk succeeded
+    fn log_outcomes(merkle_root: &[u8; 32], outcomes: &[ChunkOutcome]) -> bool {
+        let (mut succeeded, mut failed, mut submitted_feeds, mut failed_feeds) = (0, 0, 0, 0);
+        for outcome in outcomes {
+            match &outcome.result {
+                Ok(_) => {
+                    succeeded += 1;
+                    submitted_feeds += outcome.data_feeds;
+                }
+                Err(err) => {
+                    debug!("Chunk of data feeds: {} failed: {}", outcome.data_feeds, err);
+                    failed += 1;
+                    failed_feeds += outcome.data_feeds;
+                }
+            }
+        }
+        let summary = format!(
+            "Merkle root: {}, transactions succeeded: {}, failed: {}, data feeds submitted: {}, failed: {}",
+            hex::encode(merkle_root),
+            succeeded,
+            failed,
+            submitted_feeds,
+            failed_feeds
+        );
+        if failed == 0 {
+            info!("{}", summary);
+        } else {
+            warn!("{}", summary);
+        }
+        failed == 0
+    }
+
+    async fn log_update_statuses(&self, signature: &Signature, data_feeds: &[DataFeed]) {
+        let statuses = match self.get_update_statuses(signature).await {
+            Ok(statuses) => statuses,
+            Err(err) => {
+                warn!("Failed to get update statuses of: {}, error: {}", signature, err);
+                return;
+            }
+        };
+        if statuses.len() != data_feeds.len() {
+            warn!(
+                "Update statuses count: {} doesn't match data feeds count: {}, tx: {}",
+                statuses.len(),
+                data_feeds.len(),
+                signature
+            );
+        }
+        let (mut updated, mut ignored, mut created) = (0, 0, 0);
+        for (data_feed, status) in data_feeds.iter().zip(&statuses) {
+            match status {
+                UpdateStatus::Updated => updated += 1,
+                UpdateStatus::IgnoredOlder => {
+                    METRICS.feeds_ignored.inc();
+                    ignored += 1
+                }
+                UpdateStatus::Created => created += 1,
+            }
+            debug!(
+                "Data feed: {}, timestamp: {}, status: {}",
+                DataKey::from(data_feed.data_key),
+                data_feed.timestamp,
+                status
+            );
+        }
+        info!(
+            "Update statuses of: {}, updated: {}, ignored_older: {}, created: {}",
+            signature, updated, ignored, created
+        );
+    }
 
+    async fn get_update_statuses(
+        &self,
+        signature: &Signature,
+    ) -> Result<Vec<UpdateStatus>, String> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        let transaction = self
+            .rpc_client
+            .get_transaction_with_config(signature, config)
+            .await
+            .map_err(|err| err.to_string())?;
+        let return_data = transaction
+            .transaction
+            .meta
+            .and_then(|meta| Option::<UiTransactionReturnData>::from(meta.return_data))
+            .ok_or("No return data in transaction meta")?;
+        let (data, encoding) = return_data.data;
+        if encoding != UiReturnDataEncoding::Base64 {
+            return Err(format!("Unexpected return data encoding: {:?}", encoding));
+        }
+        let data = BASE64.decode(data).map_err(|err| err.to_string())?;
+        Self::parse_update_statuses(data)
+    }
+
+    fn parse_update_statuses(mut data: Vec<u8>) -> Result<Vec<UpdateStatus>, String> {
+        // The runtime truncates trailing zero bytes of the return data, so the borsh vector
+        // is restored to its full length given by the u32 length prefix before deserializing
+        data.resize(data.len().max(4), 0);
+        let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        data.resize(data.len().max(4 + len), 0);
+        Vec::<UpdateStatus>::try_from_slice(&data).map_err(|err| err.to_string())
+    }
+
+    fn log_data_feed_accounts(data_feeds: &[DataFeed], protocol_id: &[u8; 32]) {
         let pda = data_feeds
             .iter()
             .map(|data_feed| {
-                let pda = Self::get_data_key_pda(&data_feed.data_key, protocol_id);
-                format!("{}: {}", String::from_utf8_lossy(&data_feed.data_key), pda.pubkey)
+                let (pda, _) = pda::latest_update(protocol_id, &data_feed.data_key);
+                format!("{}: {}", DataKey::from(data_feed.data_key), pda)
             })
             .collect::<Vec<String>>()
             .join(", ");
         debug!("pda: [{}]", pda);
-        accounts.extend(remaining_accounts);
-    }
-
-    fn get_data_key_pda(data_key: &[u8], udf_protocol_id: &[u8]) -> AccountMeta {
-        let (asset_pda, _) = Pubkey::find_program_address(
-            &[ROOT, b"LAST_UPDATE", udf_protocol_id, data_key],
-            &udf_solana::ID,
-        );
-        AccountMeta::new(asset_pda, false)
     }
 
     pub(crate) async fn get_latest_update(&self, data_key: [u8; 32]) -> Result<LatestUpdate, ()> {
-        let client = self.get_client()?;
-
-        let program = client
-            .program(udf_solana::ID)
-            .map_err(|err| error!("Failed to get udf_solana program client: {}", err))?;
-
-        let (config, _) = Pubkey::find_program_address(
-            &[udf_solana::udf_solana::ROOT, b"CONFIG"],
-            &udf_solana::ID,
-        );
-
-        let config_acc: UdfConfig = program.account(config).await.map_err(|err| {
-            error!(
-                "Failed to get price oracle sc config account data at: {}, error: {}",
-                config, err
-            )
-        })?;
-
-        let (asset_pda, _) = Pubkey::find_program_address(
-            &[
-                udf_solana::udf_solana::ROOT,
-                b"LAST_UPDATE",
-                &config_acc.protocol_id,
-                &data_key,
-            ],
-            &udf_solana::ID,
-        );
-        debug!(
-            "Latest update requested for: {}, pda: {}",
-            String::from_utf8_lossy(&data_key),
-            asset_pda
-        );
+        let oracle_state = self.oracle_state.state().await;
+        let config = &oracle_state.config;
+        let (asset_pda, _) = pda::latest_update(&config.protocol_id, &data_key);
+        debug!("Latest update requested for: {}, pda: {}", DataKey::from(data_key), asset_pda);
         let last_update =
-            program.account::<LatestUpdateAccount>(asset_pda).await.map_err(|err| {
+            accounts::fetch_latest_update(&self.rpc_client, &config.protocol_id, &data_key)
+                .await
+                .map_err(|err| {
                 error!("Failed to get latest update account at: {}, error: {}", asset_pda, err)
             })?;
+        self.known_timestamps.record(data_key, last_update.data_timestamp).await;
 
         Ok(LatestUpdate {
             price: last_update.data,
@@ -207,7 +491,130 @@ impl DataFeedProcessor {
         })
     }
 
+    pub(crate) async fn get_latest_updates(
+        &self,
+        data_keys: &[[u8; 32]],
+    ) -> Result<Vec<Option<LatestUpdate>>, ()> {
+        let oracle_state = self.oracle_state.state().await;
+        let config = &oracle_state.config;
+        debug!("Latest updates requested for data keys: {}", data_keys.len());
+        let latest_updates =
+            accounts::fetch_latest_updates(&self.rpc_client, &config.protocol_id, data_keys)
+                .await
+                .map_err(|err| error!("Failed to get latest update accounts: {}", err))?;
+
+        let mut result = Vec::with_capacity(latest_updates.len());
+        for (data_key, latest_update) in data_keys.iter().zip(latest_updates) {
+            let latest_update = latest_update.map(|latest_update| LatestUpdate {
+                price: latest_update.data,
+                timestamp: latest_update.data_timestamp,
+            });
+            let timestamp =
+                latest_update.as_ref().map(|update| update.timestamp).unwrap_or_default();
+            self.known_timestamps.record(*data_key, timestamp).await;
+            result.push(latest_update);
+        }
+        Ok(result)
+    }
+
     pub(crate) fn get_chain_id(&self) -> u128 {
         self.publisher_config.solana.chain_id
     }
+
+    pub(crate) fn dead_letters(&self) -> &DeadLetterQueue {
+        &self.dead_letters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AnchorSerialize;
+    use UpdateStatus::{Created, IgnoredOlder, Updated};
+
+    fn serialize(statuses: &[UpdateStatus]) -> Vec<u8> {
+        statuses.to_vec().try_to_vec().unwrap()
+    }
+
+    /// Drops the trailing zero bytes the way the runtime does with the return data
+    fn truncate(mut data: Vec<u8>) -> Vec<u8> {
+        while data.last() == Some(&0) {
+            data.pop();
+        }
+        data
+    }
+
+    #[test]
+    fn parses_untruncated_statuses() {
+        let statuses = vec![Created, Updated, IgnoredOlder];
+        let parsed = DataFeedProcessor::parse_update_statuses(serialize(&statuses)).unwrap();
+        assert_eq!(parsed, statuses);
+    }
+
+    #[test]
+    fn restores_truncated_trailing_updated_statuses() {
+        let statuses = vec![IgnoredOlder, Created, Updated, Updated];
+        let data = truncate(serialize(&statuses));
+        assert_eq!(data, vec![4, 0, 0, 0, 1, 2]);
+        assert_eq!(DataFeedProcessor::parse_update_statuses(data).unwrap(), statuses);
+    }
+
+    #[test]
+    fn restores_statuses_that_are_all_updated() {
+        let statuses = vec![Updated; 3];
+        let data = truncate(serialize(&statuses));
+        assert_eq!(data, vec![3]);
+        assert_eq!(DataFeedProcessor::parse_update_statuses(data).unwrap(), statuses);
+    }
+
+    #[test]
+    fn restores_truncated_length_prefix() {
+        let statuses = vec![Updated; 256];
+        let data = truncate(serialize(&statuses));
+        assert_eq!(data, vec![0, 1]);
+        assert_eq!(DataFeedProcessor::parse_update_statuses(data).unwrap(), statuses);
+    }
+
+    #[test]
+    fn parses_empty_return_data() {
+        assert_eq!(DataFeedProcessor::parse_update_statuses(vec![]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejects_data_longer_than_length_prefix() {
+        let mut data = serialize(&[Created]);
+        data.push(1);
+        assert!(DataFeedProcessor::parse_update_statuses(data).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_status() {
+        let data = vec![2, 0, 0, 0, 1, 3];
+        assert!(DataFeedProcessor::parse_update_statuses(data).is_err());
+    }
+
+    fn outcome(data_feeds: usize, failed: bool) -> ChunkOutcome {
+        ChunkOutcome {
+            data_feeds,
+            result: if failed {
+                Err("rpc error".to_string())
+            } else {
+                Ok(vec![])
+            },
+        }
+    }
+
+    #[test]
+    fn succeeds_only_when_every_chunk_succeeded() {
+        let merkle_root = [0; 32];
+        assert!(DataFeedProcessor::log_outcomes(&merkle_root, &[]));
+        assert!(DataFeedProcessor::log_outcomes(
+            &merkle_root,
+            &[outcome(3, false), outcome(2, false)]
+        ));
+        assert!(!DataFeedProcessor::log_outcomes(
+            &merkle_root,
+            &[outcome(3, false), outcome(2, true), outcome(1, false)]
+        ));
+    }
 }
@@ -0,0 +1,116 @@
+use log::warn;
+use std::{collections::VecDeque, sync::Mutex};
+use udf_solana_client::{DataKey, MultipleUpdateMessageV2};
+
+use crate::{data::DeadLetterInfo, retry::FailureKind};
+
+/// The data feeds whose transaction failed after all the retries
+pub(crate) struct DeadLetter {
+    pub(crate) msg: MultipleUpdateMessageV2,
+    pub(crate) kind: FailureKind,
+    pub(crate) error: String,
+    pub(crate) attempts: u32,
+    pub(crate) failed_at: i64,
+}
+
+/// Keeps the latest dead letters, the oldest ones are dropped once the capacity is reached
+pub(crate) struct DeadLetterQueue {
+    entries: Mutex<VecDeque<DeadLetter>>,
+    capacity: usize,
+}
+
+impl DeadLetterQueue {
+    pub(crate) fn new(capacity: usize) -> DeadLetterQueue {
+        DeadLetterQueue {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub(crate) fn push(&self, dead_letter: DeadLetter) {
+        warn!(
+            "Data feeds: [{}] are dead lettered after attempts: {}, error: {}",
+            dead_letter
+                .msg
+                .data_feeds
+                .iter()
+                .map(|feed| DataKey::from(feed.data_key).to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            dead_letter.attempts,
+            dead_letter.error
+        );
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("Expected dead letters lock");
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(dead_letter);
+    }
+
+    pub(crate) fn infos(&self) -> Vec<DeadLetterInfo> {
+        let entries = self.entries.lock().expect("Expected dead letters lock");
+        entries.iter().map(DeadLetterInfo::from).collect()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries.lock().expect("Expected dead letters lock").clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dead_letter(id: u8) -> DeadLetter {
+        DeadLetter {
+            msg: MultipleUpdateMessageV2 {
+                merkle_root: [id; 32],
+                data_feeds: vec![],
+                signatures: vec![],
+            },
+            kind: FailureKind::Program,
+            error: "custom program error".to_string(),
+            attempts: 3,
+            failed_at: id as i64,
+        }
+    }
+
+    fn merkle_roots(queue: &DeadLetterQueue) -> Vec<u8> {
+        queue.infos().iter().map(|info| info.merkle_root[0]).collect()
+    }
+
+    #[test]
+    fn evicts_oldest_at_capacity() {
+        let queue = DeadLetterQueue::new(2);
+        for id in 1..=3 {
+            queue.push(dead_letter(id));
+        }
+        assert_eq!(merkle_roots(&queue), vec![2, 3]);
+    }
+
+    #[test]
+    fn keeps_nothing_without_capacity() {
+        let queue = DeadLetterQueue::new(0);
+        queue.push(dead_letter(1));
+        assert!(queue.infos().is_empty());
+    }
+
+    #[test]
+    fn reads_and_clears_dead_letters() {
+        let queue = DeadLetterQueue::new(4);
+        queue.push(dead_letter(1));
+        let infos = queue.infos();
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].attempts, 3);
+        assert_eq!(infos[0].failure_kind, FailureKind::Program as u32);
+        assert_eq!(infos[0].failed_at, 1);
+
+        queue.clear();
+        assert!(queue.infos().is_empty());
+        queue.push(dead_letter(2));
+        assert_eq!(merkle_roots(&queue), vec![2]);
+    }
+}
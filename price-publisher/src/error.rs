@@ -4,4 +4,37 @@ use thiserror::Error;
 pub enum PublisherError {
     #[error("Config error")]
     Config,
+    #[error("Publisher is already initialized")]
+    AlreadyInitialized,
+    #[error("Invalid config path")]
+    InvalidConfigPath,
+    #[error("Failed to start tokio runtime")]
+    Runtime,
+    #[error("Failed to start publisher")]
+    Start,
+    #[error("Invalid argument")]
+    InvalidArgument,
+    #[error("Publisher is not running")]
+    NotRunning,
+    #[error("Failed to read latest updates")]
+    LatestUpdates,
+    #[error("Publisher is called from its own thread")]
+    Reentrant,
+}
+
+impl PublisherError {
+    /// The code returned through the FFI, zero stands for success
+    pub fn code(&self) -> i32 {
+        match self {
+            PublisherError::Config => 1,
+            PublisherError::AlreadyInitialized => 2,
+            PublisherError::InvalidConfigPath => 3,
+            PublisherError::Runtime => 4,
+            PublisherError::Start => 5,
+            PublisherError::InvalidArgument => 6,
+            PublisherError::NotRunning => 7,
+            PublisherError::LatestUpdates => 8,
+            PublisherError::Reentrant => 9,
+        }
+    }
 }
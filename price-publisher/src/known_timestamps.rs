@@ -0,0 +1,134 @@
+use log::{debug, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use udf_solana_client::{accounts, DataKey, MultipleUpdateMessage};
+
+/// Keeps the latest timestamps known to be stored on chain per data key, so the data feeds the
+/// program would ignore as not newer are not paid for
+#[derive(Default)]
+pub(crate) struct KnownTimestamps {
+    timestamps: RwLock<HashMap<[u8; 32], u64>>,
+}
+
+impl KnownTimestamps {
+    /// Records the timestamp stored on chain, the older ones never replace the newer
+    pub(crate) async fn record(&self, data_key: [u8; 32], timestamp: u64) {
+        let mut timestamps = self.timestamps.write().await;
+        let known = timestamps.entry(data_key).or_default();
+        *known = (*known).max(timestamp);
+    }
+
+    /// Drops the data feeds that are not newer than the on chain state, the data keys that are
+    /// not known yet are read from the latest update accounts first
+    pub(crate) async fn filter(
+        &self,
+        rpc_client: &RpcClient,
+        protocol_id: &[u8; 32],
+        mut msg: MultipleUpdateMessage,
+    ) -> MultipleUpdateMessage {
+        self.seed(rpc_client, protocol_id, &msg).await;
+        let timestamps = self.timestamps.read().await;
+        msg.data_feeds.retain(|feed| {
+            let known = timestamps.get(&feed.data_key).copied().unwrap_or_default();
+            if feed.timestamp <= known {
+                debug!(
+                    "Data feed: {}, timestamp: {} is not newer than on chain: {}, skipped",
+                    DataKey::from(feed.data_key),
+                    feed.timestamp,
+                    known
+                );
+            }
+            feed.timestamp > known
+        });
+        msg
+    }
+
+    async fn seed(
+        &self,
+        rpc_client: &RpcClient,
+        protocol_id: &[u8; 32],
+        msg: &MultipleUpdateMessage,
+    ) {
+        let unknown: Vec<[u8; 32]> = {
+            let timestamps = self.timestamps.read().await;
+            msg.data_feeds
+                .iter()
+                .map(|feed| feed.data_key)
+                .filter(|data_key| !timestamps.contains_key(data_key))
+                .collect()
+        };
+        if unknown.is_empty() {
+            return;
+        }
+        let latest_updates =
+            match accounts::fetch_latest_updates(rpc_client, protocol_id, &unknown).await {
+                Ok(latest_updates) => latest_updates,
+                Err(err) => {
+                    warn!("Failed to read on chain timestamps of new data feeds: {}", err);
+                    return;
+                }
+            };
+        for (data_key, latest_update) in unknown.into_iter().zip(latest_updates) {
+            let timestamp = latest_update.map(|update| update.data_timestamp).unwrap_or_default();
+            self.record(data_key, timestamp).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::runtime;
+    use udf_solana_client::DataFeed;
+
+    fn data_feed(id: u8, timestamp: u64) -> DataFeed {
+        DataFeed {
+            timestamp,
+            data_key: [id; 32],
+            ..Default::default()
+        }
+    }
+
+    fn message(data_feeds: Vec<DataFeed>) -> MultipleUpdateMessage {
+        MultipleUpdateMessage {
+            data_feeds,
+            ..Default::default()
+        }
+    }
+
+    fn timestamps(msg: &MultipleUpdateMessage) -> Vec<u64> {
+        msg.data_feeds.iter().map(|feed| feed.timestamp).collect()
+    }
+
+    // Nothing listens on the port, the reads of the unknown data keys fail right away
+    fn rpc_client() -> RpcClient {
+        RpcClient::new("http://127.0.0.1:1".to_string())
+    }
+
+    #[test]
+    fn drops_data_feeds_not_newer_than_known() {
+        let runtime = runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let filtered = runtime.block_on(async {
+            let known_timestamps = KnownTimestamps::default();
+            known_timestamps.record([1; 32], 100).await;
+            known_timestamps.record([2; 32], 100).await;
+            // The older timestamp doesn't replace the newer one
+            known_timestamps.record([2; 32], 50).await;
+            let msg = message(vec![data_feed(1, 100), data_feed(2, 101), data_feed(2, 60)]);
+            known_timestamps.filter(&rpc_client(), &[0; 32], msg).await
+        });
+        assert_eq!(timestamps(&filtered), vec![101]);
+    }
+
+    #[test]
+    fn keeps_unknown_data_feeds_when_they_cant_be_read() {
+        let runtime = runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let filtered = runtime.block_on(async {
+            let known_timestamps = KnownTimestamps::default();
+            let msg = message(vec![data_feed(1, 100)]);
+            known_timestamps.filter(&rpc_client(), &[0; 32], msg).await
+        });
+        assert_eq!(timestamps(&filtered), vec![100]);
+    }
+}
@@ -2,64 +2,68 @@ mod app;
 mod config;
 mod data;
 mod data_feed_processor;
+mod dead_letter;
 mod error;
+mod known_timestamps;
+mod lookup_table;
+mod metrics;
+mod oracle_state;
+mod priority_fee;
+mod retry;
+mod runtime;
+mod status_callback;
+mod transaction_planner;
 
 extern crate udf_solana;
 
 use log::{debug, error, warn};
-use once_cell::sync::Lazy;
-use std::{slice, sync::Arc, time::Duration};
-use tokio::{
-    runtime::{self, Runtime},
-    select,
-    sync::mpsc::{unbounded_channel, UnboundedSender},
+use std::{
+    ffi::{c_char, CStr},
+    ptr, slice,
+    time::Duration,
 };
-use udf_solana::data::MultipleUpdateMessage;
+use tokio::select;
+use udf_solana::data::{DataKey, MultipleUpdateMessage};
 
 use crate::{
-    app::PublisherApp,
-    config::PublisherConfig,
-    data::{LatestUpdate, MerkleRootUpdateMultiple},
+    data::{DeadLetterInfo, LatestUpdate, LatestUpdateResult, MerkleRootUpdateMultiple},
+    error::PublisherError,
+    status_callback::StatusCallback,
 };
 
-struct PricePublisherRuntime {
-    _tokio_runtime: Runtime,
-    data_feed_msg_sender: UnboundedSender<MultipleUpdateMessage>,
-    publisher_app: Arc<PublisherApp>,
+/// Starts the publisher with the config at the given path, returns zero on success or the
+/// `PublisherError` code otherwise
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn publisher_init(config_path: *const c_char) -> i32 {
+    if config_path.is_null() {
+        error!("Error: Null config path received");
+        return PublisherError::InvalidConfigPath.code();
+    }
+    let config_path = match unsafe { CStr::from_ptr(config_path) }.to_str() {
+        Ok(config_path) => config_path,
+        Err(err) => {
+            error!("Failed to read config path: {}", err);
+            return PublisherError::InvalidConfigPath.code();
+        }
+    };
+    match runtime::init(config_path) {
+        Ok(()) => 0,
+        Err(err) => {
+            error!("Failed to init publisher: {}", err);
+            err.code()
+        }
+    }
 }
 
-static RUNTIME: Lazy<PricePublisherRuntime> = Lazy::new(|| {
-    env_logger::init();
-    println!("Tokio runtime is being started");
-    let tokio_runtime = runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .expect("Expected tokio runtime to be started");
-    let (data_feed_msg_sender, data_feed_msg_receiver) = unbounded_channel();
-
-    let config_path =
-        std::env::var("ENT_SOLANA_PUBLISHER_CONFIG").unwrap_or_else(|_| "config.yml".to_string());
-    let publisher_config = PublisherConfig::try_from_path(&config_path)
-        .map_err(|err| error!("Failed to read config from path: {}, error: {}", config_path, err))
-        .expect("Expected publisher config to be read");
-
-    let publisher_app = tokio_runtime.block_on(async move {
-        PublisherApp::try_new(publisher_config, data_feed_msg_receiver)
-            .await
-            .expect("feed_manager expected to be created well")
-    });
-    let arc_app = Arc::new(publisher_app);
-    let publisher_app = arc_app.clone();
-    tokio_runtime.spawn(async move {
-        publisher_app.execute().await;
-    });
-
-    PricePublisherRuntime {
-        _tokio_runtime: tokio_runtime,
-        data_feed_msg_sender,
-        publisher_app: arc_app,
-    }
-});
+/// Processes the data feed messages received so far and stops the publisher
+///
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn publisher_shutdown() {
+    runtime::shutdown();
+}
 
 /// # Safety
 #[allow(private_interfaces)]
@@ -71,11 +75,10 @@ pub unsafe extern "C" fn update_multiple_assets(data: *const MerkleRootUpdateMul
     }
 
     let merkle_root_update_multiple: &MerkleRootUpdateMultiple = unsafe { &*data };
-    let sender = &RUNTIME.data_feed_msg_sender;
-    let data_feed_message = MultipleUpdateMessage::from(merkle_root_update_multiple);
-    if let Err(err) = sender.send(data_feed_message) {
-        error!("Failed to send data_feed_message through the channel: {}", err);
+    let Some(runtime) = runtime::get() else {
+        return;
     };
+    runtime.send(MultipleUpdateMessage::from(merkle_root_update_multiple));
 }
 
 /// # Safety
@@ -84,25 +87,118 @@ pub unsafe extern "C" fn update_multiple_assets(data: *const MerkleRootUpdateMul
 pub unsafe extern "C" fn get_latest_update(data_key: *const u8) -> LatestUpdate {
     let data_key: &[u8] = unsafe { slice::from_raw_parts(data_key, 32) };
     let data_key: &[u8; 32] = data_key.try_into().expect("data_key should be 32 bytes long");
-    debug!("Latest update requested: {}", String::from_utf8_lossy(data_key));
-    let publisher_app = &RUNTIME.publisher_app;
-    RUNTIME._tokio_runtime.block_on(async move {
+    debug!("Latest update requested: {}", DataKey::from(*data_key));
+    let Some(runtime) = runtime::get() else {
+        return LatestUpdate::default();
+    };
+    let publisher_app = &runtime.publisher_app;
+    runtime
+        .block_on(async move {
+            select! {
+                latest_update = publisher_app.get_latest_update(*data_key) => latest_update,
+                _ = tokio::time::sleep(Duration::from_secs(2)) => {
+                    warn!("Failed to get latest update in 2 seconds");
+                    LatestUpdate::default()
+                }
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the latest updates of `len` data keys of 32 bytes each with a single request, `out` has to
+/// hold `len` results. Returns zero on success or the `PublisherError` code otherwise
+///
+/// # Safety
+#[allow(private_interfaces)]
+#[no_mangle]
+pub unsafe extern "C" fn get_latest_updates(
+    data_keys: *const [u8; 32],
+    len: usize,
+    out: *mut LatestUpdateResult,
+) -> i32 {
+    if len == 0 {
+        return 0;
+    }
+    if data_keys.is_null() || out.is_null() {
+        error!("Error: Null pointer received");
+        return PublisherError::InvalidArgument.code();
+    }
+    let data_keys: &[[u8; 32]] = unsafe { slice::from_raw_parts(data_keys, len) };
+    let out: &mut [LatestUpdateResult] = unsafe { slice::from_raw_parts_mut(out, len) };
+    debug!("Latest updates requested: {}", data_keys.len());
+    let Some(runtime) = runtime::get() else {
+        return PublisherError::NotRunning.code();
+    };
+    let publisher_app = &runtime.publisher_app;
+    let latest_updates = runtime.block_on(async move {
         select! {
-            latest_update = publisher_app.get_latest_update(*data_key) => latest_update,
+            latest_updates = publisher_app.get_latest_updates(data_keys) => latest_updates,
             _ = tokio::time::sleep(Duration::from_secs(2)) => {
-                warn!("Failed to get latest update in 2 seconds");
-                LatestUpdate::default()
+                warn!("Failed to get latest updates in 2 seconds");
+                Err(())
             }
         }
-    })
+    });
+    let latest_updates = match latest_updates {
+        Ok(Ok(latest_updates)) => latest_updates,
+        Ok(Err(())) => return PublisherError::LatestUpdates.code(),
+        Err(err) => return err.code(),
+    };
+    for (slot, latest_update) in out.iter_mut().zip(latest_updates) {
+        *slot = LatestUpdateResult::from(latest_update);
+    }
+    0
 }
 
+/// Writes the big endian chain id into `return_chain_id`, 0 when the publisher is not running
+///
 /// # Safety
+///
+/// `return_chain_id` has to be null or point to 16 writable bytes, no alignment is required
 #[allow(private_interfaces)]
 #[no_mangle]
 pub unsafe extern "C" fn get_chain_id(return_chain_id: *mut u8) {
-    let publisher_app = &RUNTIME.publisher_app;
-    let to_return = publisher_app.get_chain_id();
-    let bytes: &mut u128 = &mut *(return_chain_id as *mut u128);
-    *bytes = to_return.to_be();
+    if return_chain_id.is_null() {
+        error!("Error: Null pointer received");
+        return;
+    }
+    let to_return = runtime::get().map_or(0, |runtime| runtime.publisher_app.get_chain_id());
+    unsafe { ptr::copy_nonoverlapping(to_return.to_be_bytes().as_ptr(), return_chain_id, 16) };
+}
+
+/// Copies up to `capacity` dead letters, the oldest first, into `out` and returns the total count
+///
+/// # Safety
+#[allow(private_interfaces)]
+#[no_mangle]
+pub unsafe extern "C" fn get_dead_letters(out: *mut DeadLetterInfo, capacity: usize) -> usize {
+    let Some(runtime) = runtime::get() else {
+        return 0;
+    };
+    let dead_letters = runtime.publisher_app.dead_letters();
+    if !out.is_null() {
+        let out: &mut [DeadLetterInfo] = unsafe { slice::from_raw_parts_mut(out, capacity) };
+        for (slot, dead_letter) in out.iter_mut().zip(&dead_letters) {
+            *slot = dead_letter.clone();
+        }
+    }
+    dead_letters.len()
+}
+
+/// # Safety
+#[no_mangle]
+pub unsafe extern "C" fn clear_dead_letters() {
+    if let Some(runtime) = runtime::get() {
+        runtime.publisher_app.clear_dead_letters();
+    }
+}
+
+/// Registers the callback invoked after every chunk of the update messages is submitted, a null
+/// callback unregisters it
+///
+/// # Safety
+#[allow(private_interfaces)]
+#[no_mangle]
+pub unsafe extern "C" fn register_status_callback(callback: Option<StatusCallback>) {
+    status_callback::register(callback);
 }
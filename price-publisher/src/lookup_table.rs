@@ -0,0 +1,200 @@
+use log::{error, info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    address_lookup_table::{
+        instruction::{create_lookup_table, extend_lookup_table},
+        state::{AddressLookupTable, LOOKUP_TABLE_MAX_ADDRESSES},
+        AddressLookupTableAccount,
+    },
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use solana_tools::solana_transactor::{ix_compiler::InstructionBundle, SolanaTransactor};
+use std::{fs, io::ErrorKind, path::Path, str::FromStr};
+use tokio::sync::RwLock;
+
+const EXTEND_LOOKUP_TABLE_CHUNK_SIZE: usize = 20;
+
+/// Keeps the address lookup table used to compile v0 update transactions, the local copy only
+/// receives addresses once the extending transaction is confirmed. The table created by the
+/// publisher is persisted at the lookup table path and reused after restart
+pub(crate) struct LookupTableManager {
+    lookup_table: RwLock<AddressLookupTableAccount>,
+}
+
+impl LookupTableManager {
+    pub(crate) async fn try_new(
+        rpc_client: &RpcClient,
+        transactor: &SolanaTransactor,
+        publisher: &Keypair,
+        lookup_table: Option<Pubkey>,
+        lookup_table_path: &Path,
+    ) -> Result<LookupTableManager, ()> {
+        let lookup_table = match lookup_table.or_else(|| read_persisted_key(lookup_table_path)) {
+            Some(key) => Self::fetch_lookup_table(rpc_client, key).await?,
+            None => {
+                let lookup_table =
+                    Self::create_lookup_table(rpc_client, transactor, publisher).await?;
+                persist_key(lookup_table_path, &lookup_table.key);
+                lookup_table
+            }
+        };
+        Ok(LookupTableManager {
+            lookup_table: RwLock::new(lookup_table),
+        })
+    }
+
+    pub(crate) async fn lookup_table(&self) -> AddressLookupTableAccount {
+        self.lookup_table.read().await.clone()
+    }
+
+    /// Extends the lookup table by the addresses that are not in it yet
+    pub(crate) async fn extend(
+        &self,
+        transactor: &SolanaTransactor,
+        publisher: &Keypair,
+        addresses: impl IntoIterator<Item = Pubkey>,
+    ) -> Result<(), ()> {
+        let mut lookup_table = self.lookup_table.write().await;
+        let mut new_addresses: Vec<Pubkey> = vec![];
+        for address in addresses {
+            if !lookup_table.addresses.contains(&address) && !new_addresses.contains(&address) {
+                new_addresses.push(address);
+            }
+        }
+        let capacity = LOOKUP_TABLE_MAX_ADDRESSES.saturating_sub(lookup_table.addresses.len());
+        if new_addresses.len() > capacity {
+            warn!(
+                "Lookup table: {} is full, {} addresses are left out of it",
+                lookup_table.key,
+                new_addresses.len() - capacity
+            );
+            new_addresses.truncate(capacity);
+        }
+
+        for chunk in new_addresses.chunks(EXTEND_LOOKUP_TABLE_CHUNK_SIZE) {
+            let ix = extend_lookup_table(
+                lookup_table.key,
+                publisher.pubkey(),
+                Some(publisher.pubkey()),
+                chunk.to_vec(),
+            );
+            Self::send_instruction(transactor, publisher, ix)
+                .await
+                .map_err(|_| error!("Failed to extend lookup table: {}", lookup_table.key))?;
+            lookup_table.addresses.extend_from_slice(chunk);
+            info!("Lookup table: {} extended by {} addresses", lookup_table.key, chunk.len());
+        }
+        Ok(())
+    }
+
+    async fn fetch_lookup_table(
+        rpc_client: &RpcClient,
+        key: Pubkey,
+    ) -> Result<AddressLookupTableAccount, ()> {
+        let account = rpc_client
+            .get_account(&key)
+            .await
+            .map_err(|err| error!("Failed to get lookup table account: {}, error: {}", key, err))?;
+        let lookup_table = AddressLookupTable::deserialize(&account.data)
+            .map_err(|err| error!("Failed to deserialize lookup table: {}, error: {}", key, err))?;
+        info!("Lookup table: {} loaded with {} addresses", key, lookup_table.addresses.len());
+        Ok(AddressLookupTableAccount {
+            key,
+            addresses: lookup_table.addresses.to_vec(),
+        })
+    }
+
+    async fn create_lookup_table(
+        rpc_client: &RpcClient,
+        transactor: &SolanaTransactor,
+        publisher: &Keypair,
+    ) -> Result<AddressLookupTableAccount, ()> {
+        let recent_slot = rpc_client
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await
+            .map_err(|err| error!("Failed to get recent slot: {}", err))?;
+        let (ix, key) = create_lookup_table(publisher.pubkey(), publisher.pubkey(), recent_slot);
+        Self::send_instruction(transactor, publisher, ix)
+            .await
+            .map_err(|_| error!("Failed to create lookup table: {}", key))?;
+        info!("Lookup table: {} created", key);
+        Ok(AddressLookupTableAccount {
+            key,
+            addresses: vec![],
+        })
+    }
+
+    async fn send_instruction(
+        transactor: &SolanaTransactor,
+        publisher: &Keypair,
+        ix: Instruction,
+    ) -> Result<(), ()> {
+        let bundle = vec![InstructionBundle::new(ix, 50000)];
+        transactor
+            .send_all_instructions::<&str>(
+                None,
+                &bundle,
+                &[publisher],
+                publisher.pubkey(),
+                1,
+                &[],
+                None,
+                false,
+            )
+            .await
+            .map_err(|err| error!("Failed to process lookup table transaction: {}", err))?;
+        Ok(())
+    }
+}
+
+/// The key of the lookup table created by the publisher before, if any
+fn read_persisted_key(path: &Path) -> Option<Pubkey> {
+    let key = match fs::read_to_string(path) {
+        Ok(key) => key,
+        Err(err) if err.kind() == ErrorKind::NotFound => return None,
+        Err(err) => {
+            warn!("Failed to read lookup table from: {}, error: {}", path.display(), err);
+            return None;
+        }
+    };
+    Pubkey::from_str(key.trim())
+        .map_err(|err| {
+            warn!("Failed to parse lookup table from: {}, error: {}", path.display(), err)
+        })
+        .ok()
+}
+
+fn persist_key(path: &Path, key: &Pubkey) {
+    match fs::write(path, key.to_string()) {
+        Ok(()) => info!("Lookup table: {} persisted to: {}", key, path.display()),
+        Err(err) => error!(
+            "Failed to persist lookup table: {} to: {}, set it in the config to reuse it after \
+             restart, error: {}",
+            key,
+            path.display(),
+            err
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_persisted_key() {
+        let path = std::env::temp_dir().join(format!("lookup-table-{}", Pubkey::new_unique()));
+        assert_eq!(read_persisted_key(&path), None);
+
+        let key = Pubkey::new_unique();
+        persist_key(&path, &key);
+        assert_eq!(read_persisted_key(&path), Some(key));
+
+        fs::write(&path, "not a key").unwrap();
+        assert_eq!(read_persisted_key(&path), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
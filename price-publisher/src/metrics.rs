@@ -0,0 +1,191 @@
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{future, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// The publisher metrics, they are collected whether the endpoint is enabled or not
+pub(crate) static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub(crate) struct Metrics {
+    registry: Registry,
+    pub(crate) messages_received: IntCounter,
+    pub(crate) feeds_submitted: IntCounter,
+    pub(crate) feeds_ignored: IntCounter,
+    pub(crate) transaction_latency: Histogram,
+    pub(crate) failures: IntCounterVec,
+    pub(crate) publisher_balance: IntGauge,
+    pub(crate) last_published_timestamp: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Metrics {
+        let registry = Registry::new_custom(Some("udf_publisher".to_string()), None)
+            .expect("Expected metrics registry to be created");
+        let metrics = Metrics {
+            messages_received: IntCounter::new(
+                "messages_received_total",
+                "Update messages received from the host",
+            )
+            .expect("Expected valid metric"),
+            feeds_submitted: IntCounter::new(
+                "feeds_submitted_total",
+                "Data feeds sent in the confirmed transactions",
+            )
+            .expect("Expected valid metric"),
+            feeds_ignored: IntCounter::new(
+                "feeds_ignored_total",
+                "Data feeds not newer than the on chain state",
+            )
+            .expect("Expected valid metric"),
+            transaction_latency: Histogram::with_opts(
+                HistogramOpts::new(
+                    "transaction_latency_seconds",
+                    "Time to send and confirm an update transaction",
+                )
+                .buckets(vec![0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0]),
+            )
+            .expect("Expected valid metric"),
+            failures: IntCounterVec::new(
+                Opts::new("failures_total", "Failed update attempts by reason"),
+                &["reason"],
+            )
+            .expect("Expected valid metric"),
+            publisher_balance: IntGauge::new(
+                "publisher_balance_lamports",
+                "Balance of the publisher account",
+            )
+            .expect("Expected valid metric"),
+            last_published_timestamp: IntGaugeVec::new(
+                Opts::new("last_published_timestamp", "Timestamp of the last published data feed"),
+                &["data_key"],
+            )
+            .expect("Expected valid metric"),
+            registry,
+        };
+        metrics.register();
+        metrics
+    }
+
+    fn register(&self) {
+        let collectors: [Box<dyn prometheus::core::Collector>; 7] = [
+            Box::new(self.messages_received.clone()),
+            Box::new(self.feeds_submitted.clone()),
+            Box::new(self.feeds_ignored.clone()),
+            Box::new(self.transaction_latency.clone()),
+            Box::new(self.failures.clone()),
+            Box::new(self.publisher_balance.clone()),
+            Box::new(self.last_published_timestamp.clone()),
+        ];
+        for collector in collectors {
+            self.registry.register(collector).expect("Expected metric to be registered");
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", err);
+        }
+        buffer
+    }
+}
+
+/// Serves the metrics in the prometheus text format at any path of the address, never returns
+pub(crate) async fn serve(address: SocketAddr) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Failed to bind metrics endpoint to: {}, error: {}", address, err);
+            return future::pending().await;
+        }
+    };
+    info!("Metrics endpoint is listening on: {}", address);
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(respond(stream));
+            }
+            Err(err) => warn!("Failed to accept metrics connection: {}", err),
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream) {
+    // The request itself is irrelevant, it is read only to not reset the connection
+    let mut request = [0u8; 1024];
+    if let Err(err) = stream.read(&mut request).await {
+        debug!("Failed to read metrics request: {}", err);
+        return;
+    }
+    let body = METRICS.encode();
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        TextEncoder::new().format_type(),
+        body.len()
+    );
+    if let Err(err) = async {
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(&body).await?;
+        stream.shutdown().await
+    }
+    .await
+    {
+        debug!("Failed to write metrics response: {}", err);
+    }
+}
+
+/// Keeps the publisher balance gauge up to date, never returns
+pub(crate) async fn watch_balance(rpc_client: &RpcClient, publisher: &Pubkey, interval: Duration) {
+    loop {
+        match rpc_client.get_balance(publisher).await {
+            Ok(balance) => METRICS.publisher_balance.set(balance as i64),
+            Err(err) => warn!("Failed to get publisher balance: {}", err),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::runtime;
+
+    #[test]
+    fn responds_with_metrics() {
+        let runtime = runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let response = runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                respond(stream).await;
+            });
+            METRICS.failures.with_label_values(&["rpc"]).inc();
+            let mut client = TcpStream::connect(address).await.unwrap();
+            client.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            server.await.unwrap();
+            response
+        });
+        let (header, body) = response.split_once("\r\n\r\n").unwrap();
+        let mut lines = header.lines();
+        assert_eq!(lines.next(), Some("HTTP/1.1 200 OK"));
+        let lines: Vec<&str> = lines.collect();
+        assert!(lines.contains(&"Content-Type: text/plain; version=0.0.4"));
+        assert!(lines.contains(&format!("Content-Length: {}", body.len()).as_str()));
+        assert!(lines.contains(&"Connection: close"));
+        assert!(body.contains("# TYPE udf_publisher_failures_total counter"));
+        assert!(body.contains("udf_publisher_failures_total{reason=\"rpc\"}"));
+        assert!(body.contains("# TYPE udf_publisher_messages_received_total counter"));
+    }
+}
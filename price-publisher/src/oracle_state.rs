@@ -0,0 +1,73 @@
+use log::{debug, error, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::RwLock;
+use udf_solana::EthAddress;
+use udf_solana_client::{accounts, pda, Config};
+
+/// The price oracle accounts every update message depends on
+pub(crate) struct OracleState {
+    pub(crate) config: Config,
+    pub(crate) protocol_info: Pubkey,
+    pub(crate) transmitters: Vec<EthAddress>,
+}
+
+/// Keeps the price oracle state fetched once and refreshed on the interval, so the update
+/// messages cost no extra rpc round trips
+pub(crate) struct OracleStateCache {
+    state: RwLock<Arc<OracleState>>,
+    refresh_interval: Duration,
+}
+
+impl OracleStateCache {
+    pub(crate) async fn try_new(
+        rpc_client: &RpcClient,
+        refresh_interval: Duration,
+    ) -> Result<OracleStateCache, ()> {
+        let state = Self::fetch_state(rpc_client).await?;
+        Ok(OracleStateCache {
+            state: RwLock::new(Arc::new(state)),
+            refresh_interval,
+        })
+    }
+
+    pub(crate) async fn state(&self) -> Arc<OracleState> {
+        self.state.read().await.clone()
+    }
+
+    /// Refreshes the state on the interval, the previous state is kept when the refresh fails
+    pub(crate) async fn refresh(&self, rpc_client: &RpcClient) {
+        let mut interval = tokio::time::interval(self.refresh_interval);
+        // The first tick completes immediately and the state is already fresh at that moment
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match Self::fetch_state(rpc_client).await {
+                Ok(state) => *self.state.write().await = Arc::new(state),
+                Err(_) => warn!("Failed to refresh price oracle state, the cached one is kept"),
+            }
+        }
+    }
+
+    async fn fetch_state(rpc_client: &RpcClient) -> Result<OracleState, ()> {
+        let config = accounts::fetch_config(rpc_client).await.map_err(|err| {
+            error!("Failed to get price oracle sc config account data, error: {}", err)
+        })?;
+        let protocol_info = accounts::fetch_protocol_info(rpc_client, &config)
+            .await
+            .map_err(|err| error!("Failed to get protocol info account data, error: {}", err))?;
+        let (protocol_info_pda, _) = pda::protocol_info(&config.protocol_id, &config.endpoint);
+        let transmitters = protocol_info.transmitters();
+        debug!(
+            "Price oracle state fetched, protocol info: {}, transmitters: {}",
+            protocol_info_pda,
+            transmitters.len()
+        );
+        Ok(OracleState {
+            config,
+            protocol_info: protocol_info_pda,
+            transmitters,
+        })
+    }
+}
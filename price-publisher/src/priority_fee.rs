@@ -0,0 +1,106 @@
+use log::{debug, warn};
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+/// The max number of accounts getRecentPrioritizationFees accepts
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+/// How the compute unit price of the update transactions is chosen, the prices are in micro
+/// lamports per compute unit
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct PriorityFeeConfig {
+    /// The percentile of the recent prioritization fees paid for the written accounts
+    pub(crate) percentile: u8,
+    pub(crate) min_compute_unit_price: u64,
+    pub(crate) max_compute_unit_price: u64,
+    /// How much the price grows on every retry, in percent
+    pub(crate) retry_escalation_percent: u64,
+}
+
+impl Default for PriorityFeeConfig {
+    fn default() -> Self {
+        PriorityFeeConfig {
+            percentile: 75,
+            min_compute_unit_price: 1000,
+            max_compute_unit_price: 1_000_000,
+            retry_escalation_percent: 50,
+        }
+    }
+}
+
+/// Prices the update transactions by the recent prioritization fees of the accounts they write
+pub(crate) struct PriorityFeeStrategy {
+    config: PriorityFeeConfig,
+}
+
+impl PriorityFeeStrategy {
+    pub(crate) fn new(config: PriorityFeeConfig) -> PriorityFeeStrategy {
+        PriorityFeeStrategy { config }
+    }
+
+    /// The compute unit price of the attempt, attempts are counted from 1. The floor is used when
+    /// the recent fees can not be fetched
+    pub(crate) async fn compute_unit_price(
+        &self,
+        rpc_client: &RpcClient,
+        writable_accounts: &[Pubkey],
+        attempt: u32,
+    ) -> u64 {
+        let accounts = &writable_accounts[..writable_accounts.len().min(MAX_FEE_ACCOUNTS)];
+        let recent_price = match rpc_client.get_recent_prioritization_fees(accounts).await {
+            Ok(fees) => Self::percentile(
+                fees.iter().map(|fee| fee.prioritization_fee).collect(),
+                self.config.percentile,
+            ),
+            Err(err) => {
+                warn!("Failed to get recent prioritization fees: {}", err);
+                0
+            }
+        };
+        let mut price = recent_price.max(self.config.min_compute_unit_price);
+        for _ in 1..attempt {
+            price = price.saturating_mul(100 + self.config.retry_escalation_percent) / 100;
+        }
+        let price = price.min(self.config.max_compute_unit_price);
+        debug!("Compute unit price: {}, recent: {}, attempt: {}", price, recent_price, attempt);
+        price
+    }
+
+    fn percentile(mut fees: Vec<u64>, percentile: u8) -> u64 {
+        if fees.is_empty() {
+            return 0;
+        }
+        fees.sort_unstable();
+        let index = (fees.len() - 1) * percentile.min(100) as usize / 100;
+        fees[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_of_empty_fees_is_zero() {
+        assert_eq!(PriorityFeeStrategy::percentile(vec![], 75), 0);
+    }
+
+    #[test]
+    fn percentile_of_unsorted_fees() {
+        let fees = vec![500, 100, 400, 0, 300, 200];
+        assert_eq!(PriorityFeeStrategy::percentile(fees.clone(), 0), 0);
+        assert_eq!(PriorityFeeStrategy::percentile(fees.clone(), 50), 200);
+        assert_eq!(PriorityFeeStrategy::percentile(fees.clone(), 75), 300);
+        assert_eq!(PriorityFeeStrategy::percentile(fees.clone(), 100), 500);
+        assert_eq!(PriorityFeeStrategy::percentile(fees, 200), 500);
+    }
+
+    #[test]
+    fn percentile_of_single_fee() {
+        for percentile in [0, 50, 100] {
+            assert_eq!(PriorityFeeStrategy::percentile(vec![42], percentile), 42);
+        }
+    }
+}
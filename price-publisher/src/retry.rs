@@ -0,0 +1,230 @@
+use serde::Deserialize;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::transaction::TransactionError;
+use std::{error::Error, time::Duration};
+
+/// How the failed update transactions are retried
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct RetryConfig {
+    /// How many times a transaction is sent before its data feeds go to the dead letters
+    pub(crate) max_attempts: u32,
+    /// The delay before the first retry, doubled on every next one
+    pub(crate) initial_backoff_ms: u64,
+    pub(crate) max_backoff_ms: u64,
+    /// Up to how much of the backoff is taken off at random, in percent, so the transactions failed
+    /// together are not retried together
+    pub(crate) jitter_percent: u64,
+    /// Whether the transactions rejected by the program are retried along with the rpc failures
+    pub(crate) retry_program_errors: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+            jitter_percent: 20,
+            retry_program_errors: false,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub(crate) fn should_retry(&self, attempt: u32, kind: FailureKind) -> bool {
+        attempt < self.max_attempts && (kind == FailureKind::Rpc || self.retry_program_errors)
+    }
+
+    /// The delay after the failed attempt, attempts are counted from 1
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.jittered_backoff(attempt, rand::random())
+    }
+
+    /// The delay after the failed attempt with the jitter drawn as `random` from `[0, 1)`
+    fn jittered_backoff(&self, attempt: u32, random: f64) -> Duration {
+        let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        let backoff = self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms);
+        let jitter = backoff as f64 * self.jitter_percent.min(100) as f64 / 100.0 * random;
+        Duration::from_millis(backoff - jitter as u64)
+    }
+}
+
+/// Why the update transaction failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub(crate) enum FailureKind {
+    /// The transaction did not reach the chain or was not confirmed in time
+    Rpc = 1,
+    /// The transaction was rejected by the program
+    Program = 2,
+    /// The data feed doesn't fit into a transaction even by itself and was never sent
+    Oversized = 3,
+}
+
+impl FailureKind {
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            FailureKind::Rpc => "rpc",
+            FailureKind::Program => "program",
+            FailureKind::Oversized => "oversized",
+        }
+    }
+
+    /// Classifies the failure by the first client or transaction error among the sources of `err`,
+    /// the failures without either are the rpc ones
+    pub(crate) fn classify(err: &(dyn Error + 'static)) -> FailureKind {
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if let Some(err) = err.downcast_ref::<ClientError>() {
+                return Self::from_client_error(err.kind());
+            }
+            if let Some(err) = err.downcast_ref::<TransactionError>() {
+                return Self::from_transaction_error(err);
+            }
+            source = err.source();
+        }
+        FailureKind::Rpc
+    }
+
+    fn from_client_error(kind: &ClientErrorKind) -> FailureKind {
+        match kind {
+            ClientErrorKind::TransactionError(err) => Self::from_transaction_error(err),
+            // The preflight simulation of the transaction failed before it was sent
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) => result.err.as_ref().map_or(FailureKind::Rpc, Self::from_transaction_error),
+            _ => FailureKind::Rpc,
+        }
+    }
+
+    fn from_transaction_error(err: &TransactionError) -> FailureKind {
+        match err {
+            // The custom errors of the program along with the failures of its execution
+            TransactionError::InstructionError(..) => FailureKind::Program,
+            _ => FailureKind::Rpc,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_response::RpcSimulateTransactionResult;
+    use solana_sdk::instruction::InstructionError;
+    use std::fmt;
+
+    /// Wraps the client error the way the transactor does
+    #[derive(Debug)]
+    struct TransactorError(ClientError);
+
+    impl fmt::Display for TransactorError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Failed to send transaction: {}", self.0)
+        }
+    }
+
+    impl Error for TransactorError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn custom_program_error() -> TransactionError {
+        TransactionError::InstructionError(2, InstructionError::Custom(6003))
+    }
+
+    fn preflight_failure(err: Option<TransactionError>) -> ClientError {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: -32002,
+            message: "Transaction simulation failed".to_string(),
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                RpcSimulateTransactionResult {
+                    err,
+                    logs: None,
+                    accounts: None,
+                    units_consumed: None,
+                    return_data: None,
+                },
+            ),
+        })
+        .into()
+    }
+
+    #[test]
+    fn classifies_program_errors() {
+        assert_eq!(FailureKind::classify(&custom_program_error()), FailureKind::Program);
+        let failed =
+            TransactionError::InstructionError(0, InstructionError::ComputationalBudgetExceeded);
+        assert_eq!(FailureKind::classify(&failed), FailureKind::Program);
+        let sent = ClientError::from(custom_program_error());
+        assert_eq!(FailureKind::classify(&sent), FailureKind::Program);
+        let simulated = preflight_failure(Some(custom_program_error()));
+        assert_eq!(FailureKind::classify(&simulated), FailureKind::Program);
+        let wrapped = TransactorError(ClientError::from(custom_program_error()));
+        assert_eq!(FailureKind::classify(&wrapped), FailureKind::Program);
+    }
+
+    #[test]
+    fn classifies_rpc_errors() {
+        let io = ClientError::from(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert_eq!(FailureKind::classify(&io), FailureKind::Rpc);
+        let expired = ClientError::from(TransactionError::BlockhashNotFound);
+        assert_eq!(FailureKind::classify(&expired), FailureKind::Rpc);
+        assert_eq!(FailureKind::classify(&preflight_failure(None)), FailureKind::Rpc);
+        let node = ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: -32005,
+            message: "Node is behind".to_string(),
+            data: RpcResponseErrorData::NodeUnhealthy {
+                num_slots_behind: Some(100),
+            },
+        }));
+        assert_eq!(FailureKind::classify(&node), FailureKind::Rpc);
+        let wrapped = TransactorError(ClientError::from(ClientErrorKind::Custom(
+            "custom program error: 0x1773".to_string(),
+        )));
+        assert_eq!(FailureKind::classify(&wrapped), FailureKind::Rpc);
+        let unknown = std::io::Error::other("InstructionError");
+        assert_eq!(FailureKind::classify(&unknown), FailureKind::Rpc);
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_max() {
+        let retry = RetryConfig {
+            jitter_percent: 0,
+            ..RetryConfig::default()
+        };
+        let backoffs: Vec<u64> =
+            (1..=7).map(|attempt| retry.backoff(attempt).as_millis() as u64).collect();
+        assert_eq!(backoffs, [500, 1000, 2000, 4000, 8000, 10_000, 10_000]);
+        assert_eq!(retry.backoff(u32::MAX), Duration::from_millis(10_000));
+    }
+
+    #[test]
+    fn jitters_backoff_within_bounds() {
+        let retry = RetryConfig::default();
+        assert_eq!(retry.jittered_backoff(2, 0.0), Duration::from_millis(1000));
+        assert_eq!(retry.jittered_backoff(2, 0.5), Duration::from_millis(900));
+        assert_eq!(retry.jittered_backoff(2, 0.999_999), Duration::from_millis(801));
+        for attempt in 1..=10 {
+            let max = RetryConfig {
+                jitter_percent: 0,
+                ..retry.clone()
+            }
+            .backoff(attempt);
+            for _ in 0..100 {
+                let backoff = retry.backoff(attempt);
+                assert!(backoff <= max && backoff > max * 4 / 5, "{:?} of {:?}", backoff, max);
+            }
+        }
+        let full = RetryConfig {
+            jitter_percent: 150,
+            ..retry
+        };
+        assert_eq!(full.jittered_backoff(1, 0.999_999), Duration::from_millis(1));
+    }
+}
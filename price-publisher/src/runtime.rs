@@ -0,0 +1,237 @@
+use log::{error, info, warn};
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+use tokio::{
+    runtime::{self, Handle, Runtime},
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    task::JoinHandle,
+};
+use udf_solana::data::MultipleUpdateMessage;
+
+use crate::{app::PublisherApp, config::PublisherConfig, error::PublisherError};
+
+/// How long the shutdown waits for the received update messages to be processed
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub(crate) struct PricePublisherRuntime {
+    tokio_runtime: Mutex<Option<Runtime>>,
+    handle: Handle,
+    data_feed_msg_sender: Mutex<Option<UnboundedSender<MultipleUpdateMessage>>>,
+    app_task: Mutex<Option<JoinHandle<()>>>,
+    pub(crate) publisher_app: Arc<PublisherApp>,
+}
+
+enum RuntimeState {
+    Uninitialized,
+    Running(Arc<PricePublisherRuntime>),
+    /// The start without the explicit init failed, it's not retried until the explicit one
+    Failed,
+    ShutDown,
+}
+
+static RUNTIME: RwLock<RuntimeState> = RwLock::new(RuntimeState::Uninitialized);
+
+impl PricePublisherRuntime {
+    fn start(config_path: &str) -> Result<PricePublisherRuntime, PublisherError> {
+        let _ = env_logger::try_init();
+        info!("Tokio runtime is being started");
+        let tokio_runtime =
+            runtime::Builder::new_multi_thread().enable_all().build().map_err(|err| {
+                error!("Failed to start tokio runtime: {}", err);
+                PublisherError::Runtime
+            })?;
+        let (data_feed_msg_sender, data_feed_msg_receiver) = unbounded_channel();
+
+        let publisher_config = PublisherConfig::try_from_path(config_path)?;
+        let publisher_app = tokio_runtime
+            .block_on(PublisherApp::try_new(publisher_config, data_feed_msg_receiver))
+            .map_err(|_| PublisherError::Start)?;
+        let publisher_app = Arc::new(publisher_app);
+        let app = publisher_app.clone();
+        let app_task = tokio_runtime.spawn(async move {
+            app.execute().await;
+        });
+
+        Ok(PricePublisherRuntime {
+            handle: tokio_runtime.handle().clone(),
+            tokio_runtime: Mutex::new(Some(tokio_runtime)),
+            data_feed_msg_sender: Mutex::new(Some(data_feed_msg_sender)),
+            app_task: Mutex::new(Some(app_task)),
+            publisher_app,
+        })
+    }
+
+    /// Waits for the future on the calling thread. The threads of the runtime, the status callback
+    /// is called from, can't block on it and get `PublisherError::Reentrant` instead
+    pub(crate) fn block_on<F: std::future::Future>(
+        &self,
+        future: F,
+    ) -> Result<F::Output, PublisherError> {
+        ensure_not_reentrant()?;
+        Ok(self.handle.block_on(future))
+    }
+
+    pub(crate) fn send(&self, data_feed_msg: MultipleUpdateMessage) {
+        let sender = self.data_feed_msg_sender.lock().expect("Expected sender lock");
+        let Some(sender) = sender.as_ref() else {
+            warn!("Publisher is shutting down, data feed message is dropped");
+            return;
+        };
+        if let Err(err) = sender.send(data_feed_msg) {
+            error!("Failed to send data_feed_message through the channel: {}", err);
+        }
+    }
+
+    /// Closes the channel, waits for the received messages to be processed and stops the runtime
+    fn shutdown(&self) {
+        drop(self.data_feed_msg_sender.lock().expect("Expected sender lock").take());
+        if let Some(app_task) = self.app_task.lock().expect("Expected app task lock").take() {
+            let drained = self.handle.block_on(tokio::time::timeout(SHUTDOWN_TIMEOUT, app_task));
+            if drained.is_err() {
+                warn!(
+                    "Failed to process the received data feed messages in {:?}",
+                    SHUTDOWN_TIMEOUT
+                );
+            }
+        }
+        if let Some(tokio_runtime) =
+            self.tokio_runtime.lock().expect("Expected runtime lock").take()
+        {
+            tokio_runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+        }
+        info!("Tokio runtime is stopped");
+    }
+}
+
+/// The threads of the tokio runtime can't block on it, the publisher would wait for itself
+fn ensure_not_reentrant() -> Result<(), PublisherError> {
+    if Handle::try_current().is_ok() {
+        error!("Publisher can't wait for itself from its own thread");
+        return Err(PublisherError::Reentrant);
+    }
+    Ok(())
+}
+
+/// Starts the publisher with the config at the path, fails if it has already been started
+pub(crate) fn init(config_path: &str) -> Result<(), PublisherError> {
+    init_state(&RUNTIME, config_path)
+}
+
+fn init_state(state: &RwLock<RuntimeState>, config_path: &str) -> Result<(), PublisherError> {
+    let mut state = state.write().expect("Expected runtime state lock");
+    if let RuntimeState::Running(_) = *state {
+        return Err(PublisherError::AlreadyInitialized);
+    }
+    *state = RuntimeState::Running(Arc::new(PricePublisherRuntime::start(config_path)?));
+    Ok(())
+}
+
+/// Stops the running publisher, the later calls find no runtime until it is initialized again.
+/// Called from a thread of the runtime, it would wait for itself, so it's ignored
+pub(crate) fn shutdown() {
+    shutdown_state(&RUNTIME)
+}
+
+fn shutdown_state(state: &RwLock<RuntimeState>) {
+    if ensure_not_reentrant().is_err() {
+        return;
+    }
+    let state = std::mem::replace(
+        &mut *state.write().expect("Expected runtime state lock"),
+        RuntimeState::ShutDown,
+    );
+    match state {
+        RuntimeState::Running(runtime) => runtime.shutdown(),
+        _ => warn!("Publisher is not running, nothing to shut down"),
+    }
+}
+
+/// The running publisher. For backwards compatibility the first call without the explicit init
+/// starts it with the config from `ENT_SOLANA_PUBLISHER_CONFIG`, a failed start is remembered so
+/// the later calls don't repeat it
+pub(crate) fn get() -> Option<Arc<PricePublisherRuntime>> {
+    get_or_start(&RUNTIME, || {
+        std::env::var("ENT_SOLANA_PUBLISHER_CONFIG").unwrap_or_else(|_| "config.yml".to_string())
+    })
+}
+
+fn get_or_start(
+    state: &RwLock<RuntimeState>,
+    config_path: impl FnOnce() -> String,
+) -> Option<Arc<PricePublisherRuntime>> {
+    if let RuntimeState::Running(runtime) = &*state.read().expect("Expected runtime state lock") {
+        return Some(runtime.clone());
+    }
+    let mut state = state.write().expect("Expected runtime state lock");
+    match &*state {
+        RuntimeState::Running(runtime) => Some(runtime.clone()),
+        RuntimeState::ShutDown => {
+            error!("Publisher is shut down");
+            None
+        }
+        RuntimeState::Failed => {
+            error!("Publisher failed to start, it has to be initialized explicitly");
+            None
+        }
+        RuntimeState::Uninitialized => match PricePublisherRuntime::start(&config_path()) {
+            Ok(runtime) => {
+                let runtime = Arc::new(runtime);
+                *state = RuntimeState::Running(runtime.clone());
+                Some(runtime)
+            }
+            Err(_) => {
+                *state = RuntimeState::Failed;
+                None
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MISSING_CONFIG: &str = "missing-publisher-config.yml";
+
+    #[test]
+    fn failed_init_can_be_retried() {
+        let state = RwLock::new(RuntimeState::Uninitialized);
+        assert!(matches!(init_state(&state, MISSING_CONFIG), Err(PublisherError::Config)));
+        assert!(matches!(*state.read().unwrap(), RuntimeState::Uninitialized));
+    }
+
+    #[test]
+    fn failed_lazy_start_is_not_repeated() {
+        let state = RwLock::new(RuntimeState::Uninitialized);
+        assert!(get_or_start(&state, || MISSING_CONFIG.to_string()).is_none());
+        assert!(matches!(*state.read().unwrap(), RuntimeState::Failed));
+
+        let retried = get_or_start(&state, || panic!("Expected the failed start to be remembered"));
+        assert!(retried.is_none());
+        // The explicit init is still allowed after the failed lazy start
+        assert!(matches!(init_state(&state, MISSING_CONFIG), Err(PublisherError::Config)));
+    }
+
+    #[test]
+    fn shut_down_publisher_is_not_started_lazily() {
+        let state = RwLock::new(RuntimeState::Uninitialized);
+        shutdown_state(&state);
+        assert!(matches!(*state.read().unwrap(), RuntimeState::ShutDown));
+        assert!(get_or_start(&state, || panic!("Expected no start after the shutdown")).is_none());
+    }
+
+    #[test]
+    fn runtime_threads_cant_block_on_the_publisher() {
+        assert!(ensure_not_reentrant().is_ok());
+        let runtime = runtime::Builder::new_current_thread().build().unwrap();
+        let reentrant = runtime.block_on(async { ensure_not_reentrant() });
+        assert!(matches!(reentrant, Err(PublisherError::Reentrant)));
+
+        // The shutdown from the runtime thread leaves the publisher as it is
+        let state = RwLock::new(RuntimeState::Uninitialized);
+        runtime.block_on(async { shutdown_state(&state) });
+        assert!(matches!(*state.read().unwrap(), RuntimeState::Uninitialized));
+    }
+}
@@ -0,0 +1,125 @@
+use solana_sdk::signature::Signature;
+use std::sync::RwLock;
+
+use crate::retry::FailureKind;
+
+/// The callback the host registers to learn the outcome of every submitted chunk. It is called
+/// from the publisher threads, the report and the signatures it points to are valid only during
+/// the call. The publisher can't wait for itself there: `get_latest_update` returns the default,
+/// `get_latest_updates` the `Reentrant` error code and `publisher_shutdown` does nothing
+pub(crate) type StatusCallback = unsafe extern "C" fn(report: *const SubmissionReport);
+
+static STATUS_CALLBACK: RwLock<Option<StatusCallback>> = RwLock::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub(crate) enum SubmissionStatus {
+    Delivered = 0,
+    Failed = 1,
+}
+
+/// The outcome of a single chunk of the update message, `error_code` is zero for the delivered
+/// chunks, 1 for rpc and 2 for program errors otherwise. The data feeds too large for a
+/// transaction are reported as a failed chunk without signatures and `error_code` 3
+#[derive(Debug)]
+#[repr(C)]
+pub(crate) struct SubmissionReport {
+    pub(crate) merkle_root: [u8; 32],
+    pub(crate) signatures: *const [u8; 64],
+    pub(crate) signatures_len: usize,
+    pub(crate) data_feeds_len: usize,
+    pub(crate) status: SubmissionStatus,
+    pub(crate) error_code: u32,
+}
+
+/// Replaces the registered callback, `None` unregisters it
+pub(crate) fn register(callback: Option<StatusCallback>) {
+    *STATUS_CALLBACK.write().expect("Expected status callback lock") = callback;
+}
+
+pub(crate) fn notify(
+    merkle_root: [u8; 32],
+    signatures: &[Signature],
+    data_feeds_len: usize,
+    failure: Option<FailureKind>,
+) {
+    let Some(callback) = *STATUS_CALLBACK.read().expect("Expected status callback lock") else {
+        return;
+    };
+    let signatures: Vec<[u8; 64]> = signatures
+        .iter()
+        .map(|signature| {
+            <[u8; 64]>::try_from(signature.as_ref()).expect("Expected 64 bytes signature")
+        })
+        .collect();
+    let report = SubmissionReport {
+        merkle_root,
+        signatures: signatures.as_ptr(),
+        signatures_len: signatures.len(),
+        data_feeds_len,
+        status: match failure {
+            None => SubmissionStatus::Delivered,
+            Some(_) => SubmissionStatus::Failed,
+        },
+        error_code: failure.map(|kind| kind as u32).unwrap_or_default(),
+    };
+    unsafe { callback(&report) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{slice, sync::Mutex};
+
+    /// The copy of the report along with the signatures it points to
+    struct Received {
+        merkle_root: [u8; 32],
+        signatures: Vec<[u8; 64]>,
+        data_feeds_len: usize,
+        status: SubmissionStatus,
+        error_code: u32,
+    }
+
+    static RECEIVED: Mutex<Vec<Received>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn record(report: *const SubmissionReport) {
+        let report = unsafe { &*report };
+        let signatures = unsafe { slice::from_raw_parts(report.signatures, report.signatures_len) };
+        RECEIVED.lock().unwrap().push(Received {
+            merkle_root: report.merkle_root,
+            signatures: signatures.to_vec(),
+            data_feeds_len: report.data_feeds_len,
+            status: report.status,
+            error_code: report.error_code,
+        });
+    }
+
+    // The callback is global, so the registration is tested in a single test
+    #[test]
+    fn reports_chunks_to_registered_callback() {
+        notify([1; 32], &[], 1, None);
+        assert!(RECEIVED.lock().unwrap().is_empty());
+
+        register(Some(record));
+        let signature = Signature::from([7; 64]);
+        notify([2; 32], &[signature], 3, None);
+        notify([3; 32], &[], 1, Some(FailureKind::Oversized));
+        register(None);
+        notify([4; 32], &[signature], 1, Some(FailureKind::Rpc));
+
+        let received = RECEIVED.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let delivered = &received[0];
+        assert_eq!(delivered.merkle_root, [2; 32]);
+        assert_eq!(delivered.signatures, vec![[7; 64]]);
+        assert_eq!(delivered.data_feeds_len, 3);
+        assert_eq!(delivered.status, SubmissionStatus::Delivered);
+        assert_eq!(delivered.error_code, 0);
+
+        let failed = &received[1];
+        assert_eq!(failed.merkle_root, [3; 32]);
+        assert!(failed.signatures.is_empty());
+        assert_eq!(failed.status, SubmissionStatus::Failed);
+        assert_eq!(failed.error_code, FailureKind::Oversized as u32);
+    }
+}
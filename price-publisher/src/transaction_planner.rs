@@ -0,0 +1,354 @@
+use log::error;
+use serde::Deserialize;
+use solana_sdk::{
+    address_lookup_table::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, CompileError, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::SIGNATURE_BYTES,
+};
+use udf_solana_client::{
+    multi_proof, DataFeed, DataKey, MultiProofUpdateMessage, MultipleUpdateMessageV2,
+};
+
+/// The max number of accounts a transaction is allowed to lock
+const MAX_TRANSACTION_ACCOUNTS: usize = 64;
+/// The max compute budget a transaction is allowed to request
+const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// The per item costs the compute budget of the update instructions is estimated from, the
+/// defaults are calibrated against the update instructions on the devnet and rounded up
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct ComputeUnitsConfig {
+    /// The cost of the instruction itself along with the config, protocol info and treasury reads
+    pub(crate) base: u32,
+    /// The cost of recovering a signer with secp256k1_recover and matching it to the transmitters
+    pub(crate) signature: u32,
+    /// The cost of hashing the leaf, deriving the latest update address and creating or storing it
+    pub(crate) data_feed: u32,
+    /// The cost of hashing a single merkle proof node
+    pub(crate) proof_node: u32,
+    /// The safety margin applied on top of the estimate, in percent
+    pub(crate) margin_percent: u32,
+}
+
+impl Default for ComputeUnitsConfig {
+    fn default() -> Self {
+        ComputeUnitsConfig {
+            base: 30_000,
+            signature: 30_000,
+            data_feed: 25_000,
+            proof_node: 500,
+            margin_percent: 20,
+        }
+    }
+}
+
+impl ComputeUnitsConfig {
+    /// Estimates the compute budget of updating the data feeds
+    fn estimate(&self, signatures: usize, data_feeds: usize, proof_nodes: usize) -> u32 {
+        let compute_units = self.base as u64
+            + self.signature as u64 * signatures as u64
+            + self.data_feed as u64 * data_feeds as u64
+            + self.proof_node as u64 * proof_nodes as u64;
+        let compute_units = compute_units * (100 + self.margin_percent as u64) / 100;
+        compute_units.min(u32::MAX as u64) as u32
+    }
+}
+
+/// The formats of the update instruction the planner chooses between for every chunk
+pub(crate) enum UpdateMessage {
+    /// Every data feed carries its own merkle proof
+    Proofs(MultipleUpdateMessageV2),
+    /// The data feeds share a single deduplicated proof
+    MultiProof(MultiProofUpdateMessage),
+}
+
+/// A part of the update message that fits into a single transaction
+pub(crate) struct PlannedChunk {
+    pub(crate) ix: Instruction,
+    pub(crate) compute_units: u32,
+    pub(crate) data_feeds: Vec<DataFeed>,
+}
+
+/// The chunks of the update message along with the data feeds that don't fit into a transaction
+/// even by themselves
+pub(crate) struct Plan {
+    pub(crate) chunks: Vec<PlannedChunk>,
+    pub(crate) oversized: Vec<DataFeed>,
+}
+
+/// Splits the update message into the instructions that fit into a transaction each, packing as
+/// many data feeds as the transaction size, the account limit and the compute budget allow. Every
+/// chunk is sent in the format of the smaller transaction, the multiproof one wins once the proofs
+/// of the data feeds share nodes
+pub(crate) fn plan_chunks(
+    payer: &Pubkey,
+    lookup_table: &AddressLookupTableAccount,
+    compute_units: &ComputeUnitsConfig,
+    msg: &MultipleUpdateMessageV2,
+    build_ix: impl Fn(UpdateMessage) -> Instruction,
+) -> Plan {
+    let mut plan = Plan {
+        chunks: vec![],
+        oversized: vec![],
+    };
+    let mut current: Option<PlannedChunk> = None;
+    for data_feed in &msg.data_feeds {
+        let mut data_feeds =
+            current.as_ref().map(|chunk| chunk.data_feeds.clone()).unwrap_or_default();
+        data_feeds.push(data_feed.clone());
+        if let Some(chunk) =
+            plan_chunk(payer, lookup_table, compute_units, msg, data_feeds, &build_ix)
+        {
+            current = Some(chunk);
+            continue;
+        }
+        plan.chunks.extend(current.take());
+        current =
+            plan_chunk(payer, lookup_table, compute_units, msg, vec![data_feed.clone()], &build_ix);
+        if current.is_none() {
+            error!(
+                "Data feed: {} does not fit into a transaction",
+                DataKey::from(data_feed.data_key)
+            );
+            plan.oversized.push(data_feed.clone());
+        }
+    }
+    plan.chunks.extend(current);
+    plan
+}
+
+fn plan_chunk(
+    payer: &Pubkey,
+    lookup_table: &AddressLookupTableAccount,
+    compute_units: &ComputeUnitsConfig,
+    msg: &MultipleUpdateMessageV2,
+    data_feeds: Vec<DataFeed>,
+    build_ix: &impl Fn(UpdateMessage) -> Instruction,
+) -> Option<PlannedChunk> {
+    let mut chunk_msg = msg.clone();
+    chunk_msg.data_feeds = data_feeds.clone();
+    let multi_proof = multi_proof::from_update_message(&chunk_msg);
+
+    let signatures = msg.signatures.len();
+    let proof_nodes = data_feeds.iter().map(|feed| feed.merkle_proof.len()).sum();
+    let mut candidates = vec![(
+        compute_units.estimate(signatures, data_feeds.len(), proof_nodes),
+        UpdateMessage::Proofs(chunk_msg),
+    )];
+    if let Some(multi_proof) = multi_proof {
+        // every proof flag stands for a single hashed pair of nodes
+        let proof_nodes = multi_proof.proof_flags.len();
+        candidates.push((
+            compute_units.estimate(signatures, data_feeds.len(), proof_nodes),
+            UpdateMessage::MultiProof(multi_proof),
+        ));
+    }
+
+    candidates
+        .into_iter()
+        .filter(|(compute_units, _)| *compute_units <= MAX_COMPUTE_UNITS)
+        .filter_map(|(compute_units, update_msg)| {
+            let ix = build_ix(update_msg);
+            // the program ids of the update and compute budget instructions are locked as well
+            if ix.accounts.len() + 2 > MAX_TRANSACTION_ACCOUNTS {
+                return None;
+            }
+            let size = transaction_size(payer, lookup_table, &ix).ok()?;
+            if size > PACKET_DATA_SIZE {
+                return None;
+            }
+            Some((
+                size,
+                PlannedChunk {
+                    ix,
+                    compute_units,
+                    data_feeds: data_feeds.clone(),
+                },
+            ))
+        })
+        .min_by_key(|(size, _)| *size)
+        .map(|(_, chunk)| chunk)
+}
+
+/// The serialized size of the v0 transaction the transactor compiles for the instruction
+fn transaction_size(
+    payer: &Pubkey,
+    lookup_table: &AddressLookupTableAccount,
+    ix: &Instruction,
+) -> Result<usize, CompileError> {
+    let ixs = [
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS),
+        ComputeBudgetInstruction::set_compute_unit_price(u64::MAX),
+        ix.clone(),
+    ];
+    let message =
+        v0::Message::try_compile(payer, &ixs, std::slice::from_ref(lookup_table), Hash::default())?;
+    let signatures = message.header.num_required_signatures as usize;
+    // the signature count is a compact u16, a single byte below 128 signatures
+    Ok(1 + signatures * SIGNATURE_BYTES + VersionedMessage::V0(message).serialize().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::AccountMeta;
+    use udf_solana_client::{instructions, TransmitterSignatureV2};
+
+    fn data_feed(index: u8, proof_nodes: usize) -> DataFeed {
+        DataFeed {
+            timestamp: 1,
+            data_key: [index; 32],
+            data: [index; 32],
+            merkle_proof: vec![[index; 32]; proof_nodes],
+        }
+    }
+
+    fn update_message(data_feeds: Vec<DataFeed>) -> MultipleUpdateMessageV2 {
+        let signature = TransmitterSignatureV2 {
+            v: 27,
+            r: [1; 32],
+            s: [1; 32],
+        };
+        MultipleUpdateMessageV2 {
+            merkle_root: [0; 32],
+            data_feeds,
+            signatures: vec![signature; 3],
+        }
+    }
+
+    fn lookup_table(addresses: Vec<Pubkey>) -> AddressLookupTableAccount {
+        AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses,
+        }
+    }
+
+    fn update_ix(msg: UpdateMessage) -> Instruction {
+        let UpdateMessage::Proofs(msg) = msg else {
+            panic!("Expected no multiproof of the unrelated data feeds");
+        };
+        instructions::update_multiple_assets_v2(
+            &Pubkey::default(),
+            &[0; 32],
+            &Pubkey::default(),
+            msg,
+        )
+    }
+
+    /// The instruction that only locks an account per data feed, so the transaction size doesn't
+    /// limit the chunks with these accounts in the lookup table. The data keys are non zero not to
+    /// clash with the program id
+    fn accounts_ix(msg: UpdateMessage) -> Instruction {
+        let UpdateMessage::Proofs(msg) = msg else {
+            panic!("Expected no multiproof of the unrelated data feeds");
+        };
+        let accounts = msg
+            .data_feeds
+            .iter()
+            .map(|feed| AccountMeta::new(Pubkey::new_from_array(feed.data_key), false))
+            .collect();
+        Instruction::new_with_bytes(Pubkey::default(), &[], accounts)
+    }
+
+    fn data_feed_accounts(data_feeds: &[DataFeed]) -> Vec<Pubkey> {
+        data_feeds.iter().map(|feed| Pubkey::new_from_array(feed.data_key)).collect()
+    }
+
+    fn chunk_sizes(plan: &Plan) -> Vec<usize> {
+        plan.chunks.iter().map(|chunk| chunk.data_feeds.len()).collect()
+    }
+
+    #[test]
+    fn packs_data_feeds_up_to_transaction_size() {
+        let payer = Pubkey::new_unique();
+        let lookup_table = lookup_table(vec![]);
+        let compute_units = ComputeUnitsConfig::default();
+        let msg = update_message((0..20).map(|index| data_feed(index, 4)).collect());
+
+        let plan = plan_chunks(&payer, &lookup_table, &compute_units, &msg, update_ix);
+        assert!(plan.chunks.len() > 1);
+        assert!(plan.oversized.is_empty());
+        let planned: Vec<DataFeed> =
+            plan.chunks.iter().flat_map(|chunk| chunk.data_feeds.clone()).collect();
+        assert_eq!(planned.len(), msg.data_feeds.len());
+        for (planned, data_feed) in planned.iter().zip(&msg.data_feeds) {
+            assert_eq!(planned.data_key, data_feed.data_key);
+        }
+
+        for (chunk, next) in plan.chunks.iter().zip(plan.chunks.iter().skip(1)) {
+            assert!(
+                transaction_size(&payer, &lookup_table, &chunk.ix).unwrap() <= PACKET_DATA_SIZE
+            );
+            // the chunk is full, the first data feed of the next one would not fit into it
+            let mut data_feeds = chunk.data_feeds.clone();
+            data_feeds.push(next.data_feeds[0].clone());
+            let ix = update_ix(UpdateMessage::Proofs(update_message(data_feeds)));
+            assert!(transaction_size(&payer, &lookup_table, &ix).unwrap() > PACKET_DATA_SIZE);
+        }
+    }
+
+    #[test]
+    fn packs_data_feeds_up_to_account_limit() {
+        let compute_units = ComputeUnitsConfig {
+            base: 0,
+            signature: 0,
+            data_feed: 0,
+            proof_node: 0,
+            margin_percent: 0,
+        };
+        let msg = update_message((1..=130).map(|index| data_feed(index, 0)).collect());
+        let lookup_table = lookup_table(data_feed_accounts(&msg.data_feeds));
+
+        let plan =
+            plan_chunks(&Pubkey::new_unique(), &lookup_table, &compute_units, &msg, accounts_ix);
+        assert_eq!(chunk_sizes(&plan), vec![62, 62, 6]);
+        assert!(plan.oversized.is_empty());
+    }
+
+    #[test]
+    fn packs_data_feeds_up_to_compute_budget() {
+        let compute_units = ComputeUnitsConfig {
+            base: 10_000,
+            signature: 10_000,
+            data_feed: 100_000,
+            proof_node: 0,
+            margin_percent: 0,
+        };
+        let msg = update_message((1..=30).map(|index| data_feed(index, 0)).collect());
+        let lookup_table = lookup_table(data_feed_accounts(&msg.data_feeds));
+
+        let plan =
+            plan_chunks(&Pubkey::new_unique(), &lookup_table, &compute_units, &msg, accounts_ix);
+        // 40_000 of the instruction and the signatures leave the budget for 13 data feeds
+        assert_eq!(chunk_sizes(&plan), vec![13, 13, 4]);
+        assert_eq!(plan.chunks[0].compute_units, 1_340_000);
+        assert_eq!(plan.chunks[2].compute_units, 440_000);
+    }
+
+    #[test]
+    fn applies_compute_units_margin() {
+        let compute_units = ComputeUnitsConfig::default();
+        // (30_000 + 3 * 30_000 + 2 * 25_000 + 10 * 500) * 1.2
+        assert_eq!(compute_units.estimate(3, 2, 10), 210_000);
+    }
+
+    #[test]
+    fn reports_oversized_data_feeds() {
+        let payer = Pubkey::new_unique();
+        let lookup_table = lookup_table(vec![]);
+        let compute_units = ComputeUnitsConfig::default();
+        let msg = update_message(vec![data_feed(0, 1), data_feed(1, 40), data_feed(2, 1)]);
+
+        let plan = plan_chunks(&payer, &lookup_table, &compute_units, &msg, update_ix);
+        assert_eq!(plan.oversized.len(), 1);
+        assert_eq!(plan.oversized[0].data_key, [1; 32]);
+        // the data feeds around the oversized one are still planned
+        assert_eq!(chunk_sizes(&plan), vec![1, 1]);
+    }
+}
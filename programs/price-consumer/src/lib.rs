@@ -1,44 +1,41 @@
 use anchor_lang::prelude::*;
+use udf_solana_consumer::data::DataKey;
 
 declare_id!("3r5ixGQu8DRmJWgFEjwnDUQ6yasfYFXDsUbqkA6gkRtv");
 
 #[program]
 pub mod price_consumer {
     use super::*;
-    use anchor_lang::solana_program::{
-        instruction::Instruction,
-        program::{get_return_data, invoke},
-    };
+    use udf_solana_consumer::cpi;
 
-    pub fn consume_price(ctx: Context<ConsumePrice>, asset: String) -> Result<()> {
-        let payload =
-            asset.try_to_vec().expect("Asset is expected be converted to the vec of bytes");
-        let data = [&sighash("global", "last_price")[..], &payload[..]].concat();
-        let ix = Instruction {
-            program_id: ctx.accounts.price_oracle.key(),
-            accounts: ctx.accounts.latest_update.to_account_metas(Some(false)),
-            data,
-        };
-        invoke(&ix, &[ctx.accounts.latest_update.to_account_info()])?;
-        let (_, data) = get_return_data().expect("Data expected to be gotten from price oracle");
-        let (price, timestamp) = <([u8; 32], u64)>::try_from_slice(&data)
-            .expect("Expected price and timeout to be deserialized with borsh");
-        let (_, price_right) = price.split_at(16);
-        let price = u128::try_from_slice(price_right).unwrap().to_be();
-        msg!("Price of: {} is: {} at: {}", asset, price, timestamp);
+    pub fn consume_price(ctx: Context<ConsumePrice>, data_key: DataKey) -> Result<()> {
+        // The treasury is created along with the first subscription or publisher reward, the read
+        // fee can't be paid before that
+        let treasury = ctx.accounts.treasury.to_account_info();
+        let treasury = (treasury.owner == ctx.accounts.price_oracle.key).then_some(treasury);
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.price_oracle.to_account_info(),
+            cpi::LastPriceAccounts {
+                last_price: ctx.accounts.latest_update.to_account_info(),
+                config: ctx.accounts.config.to_account_info(),
+                payer: Some(ctx.accounts.payer.to_account_info()),
+                subscription: None,
+                treasury,
+                system_program: Some(ctx.accounts.system_program.to_account_info()),
+            },
+        );
+        let price = cpi::last_price_v2(cpi_ctx, data_key)?;
+        msg!(
+            "Price of: {} is: {} at: {}, status: {}",
+            data_key,
+            price.value(),
+            price.timestamp,
+            price.status
+        );
         Ok(())
     }
 }
 
-fn sighash(namespace: &str, name: &str) -> [u8; 8] {
-    let preimage = format!("{}:{}", namespace, name);
-    let mut sighash = [0u8; 8];
-    sighash.copy_from_slice(
-        &anchor_lang::solana_program::hash::hash(preimage.as_bytes()).to_bytes()[..8],
-    );
-    sighash
-}
-
 pub const ROOT: &[u8] = b"UDF0";
 
 #[cfg(not(feature = "mainnet"))]
@@ -47,14 +44,20 @@ const UDF_PROTOCOL_ID: &[u8] = b"universal-data-feeds3\x00\x00\x00\x00\x00\x00\x
 const UDF_PROTOCOL_ID: &[u8] = b"universal-data-feeds\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
 
 #[derive(Accounts)]
-#[instruction(asset: String)]
+#[instruction(data_key: DataKey)]
 pub struct ConsumePrice<'info> {
     #[account(signer, mut)]
     payer: Signer<'info>,
     /// CHECK: This is an external price oracle, and no specific owner is expected. The account is marked as executable to ensure it's a program.
     #[account(executable)]
     price_oracle: UncheckedAccount<'info>,
-    /// CHECK: This account is derived using specific seeds, including the asset. Ensure the seeds match the provided asset to trust the account.
-    #[account(seeds = [ROOT, b"LAST_UPDATE", UDF_PROTOCOL_ID, asset.as_bytes()], bump, seeds::program = price_oracle)]
+    /// CHECK: This account is derived using specific seeds, including the data key. Ensure the seeds match the provided data key to trust the account.
+    #[account(seeds = [ROOT, b"LAST_UPDATE", UDF_PROTOCOL_ID, &data_key.key], bump, seeds::program = price_oracle)]
     latest_update: UncheckedAccount<'info>,
+    /// CHECK: This is a configuration account that is specifically determined by the price_oracle program
+    config: UncheckedAccount<'info>,
+    /// CHECK: This is the treasury of the price_oracle program the read fee is paid to, if it's enabled
+    #[account(mut)]
+    treasury: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
 }
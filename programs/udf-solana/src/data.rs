@@ -1,11 +1,86 @@
 use anchor_lang::prelude::*;
 use derive_more::Display;
-use std::{io::Write, mem::size_of};
+use std::{fmt, mem::size_of, str::FromStr};
 
-use crate::{error::CustomError, EthAddress};
+use crate::error::CustomError;
+pub use crate::photon::ProtocolInfo;
 
 type Bytes32 = [u8; 32];
 
+/// The canonical key of a data feed: the printable ASCII asset name, e.g. `BTC/USD`, padded with
+/// zeros to 32 bytes. The latest update accounts are derived from it
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DataKey {
+    pub key: [u8; 32],
+}
+
+impl DataKey {
+    pub const MAX_LEN: usize = 32;
+
+    pub fn from_asset(asset: &str) -> Result<Self> {
+        require!(
+            !asset.is_empty()
+                && asset.len() <= Self::MAX_LEN
+                && asset.bytes().all(|byte| byte.is_ascii_graphic()),
+            CustomError::InvalidDataKey
+        );
+        let mut key = [0u8; 32];
+        key[..asset.len()].copy_from_slice(asset.as_bytes());
+        Ok(DataKey { key })
+    }
+
+    /// The asset name, if the key is canonically encoded
+    pub fn asset(&self) -> Option<&str> {
+        let len = self.key.iter().position(|byte| *byte == 0).unwrap_or(Self::MAX_LEN);
+        let (asset, padding) = self.key.split_at(len);
+        if len == 0 || padding.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        std::str::from_utf8(asset)
+            .ok()
+            .filter(|asset| asset.bytes().all(|byte| byte.is_ascii_graphic()))
+    }
+
+    pub fn is_canonical(&self) -> bool {
+        self.asset().is_some()
+    }
+}
+
+impl fmt::Display for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.asset() {
+            Some(asset) => f.write_str(asset),
+            None => write!(f, "0x{}", hex::encode(self.key)),
+        }
+    }
+}
+
+impl FromStr for DataKey {
+    type Err = Error;
+
+    fn from_str(asset: &str) -> Result<Self> {
+        DataKey::from_asset(asset)
+    }
+}
+
+impl From<[u8; 32]> for DataKey {
+    fn from(key: [u8; 32]) -> Self {
+        DataKey { key }
+    }
+}
+
+impl From<DataKey> for [u8; 32] {
+    fn from(data_key: DataKey) -> Self {
+        data_key.key
+    }
+}
+
+impl AsRef<[u8]> for DataKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.key
+    }
+}
+
 #[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
 #[display(
     fmt = "{{ merkle_root: {}, data_feeds: [{}], signatures: [{}] }}",
@@ -19,6 +94,36 @@ pub struct MultipleUpdateMessage {
     pub signatures: Vec<TransmitterSignature>,
 }
 
+/// The version of `MultipleUpdateMessage` with fixed size signatures, which saves the length
+/// prefixes of the signature components in the transaction
+#[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(
+    fmt = "{{ merkle_root: {}, data_feeds: [{}], signatures: [{}] }}",
+    "hex::encode(merkle_root)",
+    "data_feeds.iter().map(|feed| format!(\"{}\", feed)).collect::<Vec<String>>().join(\", \")",
+    "signatures.iter().map(|signature| format!(\"{}\", signature)).collect::<Vec<String>>().join(\", \")",
+)]
+pub struct MultipleUpdateMessageV2 {
+    pub merkle_root: [u8; 32],
+    pub data_feeds: Vec<DataFeed>,
+    pub signatures: Vec<TransmitterSignatureV2>,
+}
+
+impl TryFrom<MultipleUpdateMessage> for MultipleUpdateMessageV2 {
+    type Error = CustomError;
+    fn try_from(message: MultipleUpdateMessage) -> std::result::Result<Self, Self::Error> {
+        Ok(MultipleUpdateMessageV2 {
+            merkle_root: message.merkle_root,
+            data_feeds: message.data_feeds,
+            signatures: message
+                .signatures
+                .iter()
+                .map(TransmitterSignatureV2::try_from)
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
 #[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
 #[display(
     fmt = "{{ merkle_root: {}, data_feed: {}, signatures: [{}] }}",
@@ -32,11 +137,40 @@ pub struct LastPriceMessage {
     pub signatures: Vec<TransmitterSignature>,
 }
 
+/// The version of `LastPriceMessage` with fixed size signatures
+#[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(
+    fmt = "{{ merkle_root: {}, data_feed: {}, signatures: [{}] }}",
+    "hex::encode(merkle_root)",
+    data_feed,
+    "signatures.iter().map(|signature| format!(\"{}\", signature)).collect::<Vec<String>>().join(\", \")",
+)]
+pub struct LastPriceMessageV2 {
+    pub merkle_root: [u8; 32],
+    pub data_feed: DataFeed,
+    pub signatures: Vec<TransmitterSignatureV2>,
+}
+
+impl TryFrom<LastPriceMessage> for LastPriceMessageV2 {
+    type Error = CustomError;
+    fn try_from(message: LastPriceMessage) -> std::result::Result<Self, Self::Error> {
+        Ok(LastPriceMessageV2 {
+            merkle_root: message.merkle_root,
+            data_feed: message.data_feed,
+            signatures: message
+                .signatures
+                .iter()
+                .map(TransmitterSignatureV2::try_from)
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
 #[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
 #[display(
     fmt = "{{ timestamp: {}, data_key: {}, data: {}, merkle_proof: [{}] }}",
     timestamp,
-    "String::from_utf8_lossy(data_key.as_ref())",
+    "DataKey::from(*data_key)",
     "hex::encode(data)",
     "merkle_proof.iter().map(hex::encode).collect::<Vec<String>>().join(\", \")"
 )]
@@ -47,6 +181,70 @@ pub struct DataFeed {
     pub merkle_proof: Vec<[u8; 32]>,
 }
 
+#[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(
+    fmt = "{{ merkle_root: {}, data_feeds: [{}], proof: [{}], proof_flags: [{}], signatures: [{}] }}",
+    "hex::encode(merkle_root)",
+    "data_feeds.iter().map(|feed| format!(\"{}\", feed)).collect::<Vec<String>>().join(\", \")",
+    "proof.iter().map(hex::encode).collect::<Vec<String>>().join(\", \")",
+    "proof_flags.iter().map(|flag| flag.to_string()).collect::<Vec<String>>().join(\", \")",
+    "signatures.iter().map(|signature| format!(\"{}\", signature)).collect::<Vec<String>>().join(\", \")",
+)]
+pub struct MultiProofUpdateMessage {
+    pub merkle_root: [u8; 32],
+    pub data_feeds: Vec<DataFeedLeaf>,
+    pub proof: Vec<[u8; 32]>,
+    pub proof_flags: Vec<bool>,
+    pub signatures: Vec<TransmitterSignatureV2>,
+}
+
+#[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(
+    fmt = "{{ timestamp: {}, data_key: {}, data: {} }}",
+    timestamp,
+    "DataKey::from(*data_key)",
+    "hex::encode(data)"
+)]
+pub struct DataFeedLeaf {
+    pub timestamp: u64,
+    pub data_key: [u8; 32],
+    pub data: [u8; 32],
+}
+
+#[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(
+    fmt = "{{ merkle_root: {}, data_feeds: [{}], signatures: [{}] }}",
+    "hex::encode(merkle_root)",
+    "data_feeds.iter().map(|feed| format!(\"{}\", feed)).collect::<Vec<String>>().join(\", \")",
+    "signatures.iter().map(|signature| format!(\"{}\", signature)).collect::<Vec<String>>().join(\", \")",
+)]
+pub struct ConfidenceUpdateMessage {
+    pub merkle_root: [u8; 32],
+    pub data_feeds: Vec<ConfidenceDataFeed>,
+    pub signatures: Vec<TransmitterSignatureV2>,
+}
+
+/// The data feed that also carries the spread between sources and the number of sources the
+/// data is aggregated from
+#[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(
+    fmt = "{{ timestamp: {}, data_key: {}, data: {}, confidence: {}, sources: {}, merkle_proof: [{}] }}",
+    timestamp,
+    "DataKey::from(*data_key)",
+    "hex::encode(data)",
+    "hex::encode(confidence)",
+    sources,
+    "merkle_proof.iter().map(hex::encode).collect::<Vec<String>>().join(\", \")"
+)]
+pub struct ConfidenceDataFeed {
+    pub timestamp: u64,
+    pub data_key: [u8; 32],
+    pub data: [u8; 32],
+    pub confidence: [u8; 32],
+    pub sources: u16,
+    pub merkle_proof: Vec<[u8; 32]>,
+}
+
 #[derive(Clone, Display, AnchorSerialize, AnchorDeserialize, Debug)]
 #[display(fmt = "{{ {:x}{}{} }}", v, "hex::encode(r)", "hex::encode(s)")]
 pub struct TransmitterSignature {
@@ -55,65 +253,62 @@ pub struct TransmitterSignature {
     pub s: Vec<u8>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct ProtocolInfo {
-    is_init: bool,
-    pub consensus_target_rate: u64,
-    protocol_address: Pubkey,
-    pub transmitters: Box<[EthAddress; 20]>,
-    executors: Box<[Pubkey; 20]>,
-    proposers: Box<[Pubkey; 20]>,
-}
-
-impl AccountSerialize for ProtocolInfo {
-    fn try_serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        if writer.write_all(&[40, 62, 222, 136, 36, 92, 1, 233]).is_err() {
-            return Err(ErrorCode::AccountDidNotSerialize.into());
-        }
-        if AnchorSerialize::serialize(self, writer).is_err() {
-            return Err(ErrorCode::AccountDidNotSerialize.into());
-        }
-        Ok(())
-    }
+#[derive(Clone, Copy, Display, AnchorSerialize, AnchorDeserialize, Debug, Default)]
+#[display(fmt = "{{ {:x}{}{} }}", v, "hex::encode(r)", "hex::encode(s)")]
+pub struct TransmitterSignatureV2 {
+    pub v: u8,
+    pub r: [u8; 32],
+    pub s: [u8; 32],
 }
 
-impl AccountDeserialize for ProtocolInfo {
-    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
-        if buf.len() < [40, 62, 222, 136, 36, 92, 1, 233].len() {
-            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
-        }
-
-        Self::try_deserialize_unchecked(buf)
-    }
-
-    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
-        let mut data: &[u8] = &buf[8..];
-        AnchorDeserialize::deserialize(&mut data)
-            .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+impl TryFrom<&TransmitterSignature> for TransmitterSignatureV2 {
+    type Error = CustomError;
+    fn try_from(signature: &TransmitterSignature) -> std::result::Result<Self, Self::Error> {
+        Ok(TransmitterSignatureV2 {
+            v: signature.v,
+            r: signature
+                .r
+                .as_slice()
+                .try_into()
+                .map_err(|_| CustomError::InvalidSignatureLength)?,
+            s: signature
+                .s
+                .as_slice()
+                .try_into()
+                .map_err(|_| CustomError::InvalidSignatureLength)?,
+        })
     }
 }
 
-impl anchor_lang::Discriminator for ProtocolInfo {
-    // discriminator as it was defined in the photon ccm program, to make possible to read it
-    const DISCRIMINATOR: [u8; 8] = [40, 62, 222, 136, 36, 92, 1, 233];
+#[derive(Clone, Copy, Display, AnchorSerialize, AnchorDeserialize, Debug, PartialEq, Eq)]
+pub enum UpdateStatus {
+    Updated,
+    IgnoredOlder,
+    Created,
 }
 
-#[automatically_derived]
-impl Owner for ProtocolInfo {
-    fn owner() -> Pubkey {
-        // owner as it was defined in the photon ccm program
-        Pubkey::new_from_array([
-            12u8, 50u8, 145u8, 223u8, 16u8, 33u8, 233u8, 37u8, 119u8, 186u8, 206u8, 30u8, 187u8,
-            117u8, 189u8, 70u8, 23u8, 0u8, 141u8, 139u8, 21u8, 92u8, 169u8, 187u8, 124u8, 139u8,
-            89u8, 86u8, 127u8, 197u8, 95u8, 163u8,
-        ])
-    }
+/// The status of the feed returned along with its price by the reads
+#[derive(
+    Clone, Copy, Display, AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq,
+)]
+pub enum FeedStatus {
+    #[default]
+    Fresh,
+    /// The feed isn't updated within its heartbeat interval
+    Stale,
+    /// The feed is paused by the admin
+    Paused,
 }
 
-impl ProtocolInfo {
-    pub fn transmitters(&self) -> Vec<EthAddress> {
-        self.transmitters.into_iter().take_while(|k| k != &EthAddress::default()).collect()
-    }
+#[derive(
+    Clone, Copy, Display, AnchorSerialize, AnchorDeserialize, Debug, Default, PartialEq, Eq,
+)]
+pub enum SignatureScheme {
+    /// keccak256("\x19Ethereum Signed Message:\n32" || merkle_root), the same on every chain
+    #[default]
+    EthSignedMessage,
+    /// EIP-712 style digest bound to the protocol id, chain id, program id and domain version
+    DomainSeparated,
 }
 
 #[account]
@@ -122,18 +317,145 @@ pub struct Config {
     pub admin: Pubkey,
     pub endpoint: Pubkey,
     pub protocol_id: [u8; 32],
+    pub signature_scheme: SignatureScheme,
+    pub chain_id: u128,
+    pub extension: ConfigExtension,
 }
 
 impl Config {
-    pub const LEN: usize = 8 + size_of::<Pubkey>() * 3 + size_of::<Bytes32>();
+    // Configs created before the extension are shorter and have to be reallocated by
+    // `migrate_config` to be written, the spare bytes leave space for the fields added later
+    pub const LEN: usize = 8
+        + size_of::<Pubkey>() * 2
+        + size_of::<Bytes32>()
+        + size_of::<SignatureScheme>()
+        + size_of::<u128>()
+        + ConfigExtension::LEN
+        + 16;
+}
+
+/// The config fields added after the first deployment. They don't fit into the configs created
+/// before them, which are read with the missing bytes as zeros until `migrate_config` reallocates
+/// them, so the price updates and reads keep working through the upgrade
+#[derive(AnchorSerialize, Clone, Copy, Debug, Default)]
+pub struct ConfigExtension {
+    /// The only publisher reimbursed from the treasury, nobody is reimbursed while it's the
+    /// default key
+    pub rewarded_publisher: Pubkey,
+    /// Lamports the publisher receives from the treasury for every stored data feed
+    pub update_reward: u64,
+    /// The most lamports the publisher is reimbursed with per instruction, including the rent of
+    /// the created latest update accounts
+    pub reimbursement_cap: u64,
+    /// Lamports charged to the treasury for every `last_price` read without a subscription, reads
+    /// are free when it's zero
+    pub read_fee: u64,
+    /// Lamports a subscription costs per day, subscriptions are disabled when it's zero
+    pub subscription_daily_fee: u64,
+}
+
+impl ConfigExtension {
+    pub const LEN: usize = size_of::<Pubkey>() + size_of::<u64>() * 4;
+}
+
+impl AnchorDeserialize for ConfigExtension {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut bytes = [0u8; Self::LEN];
+        let mut len = 0;
+        while len < bytes.len() {
+            match reader.read(&mut bytes[len..])? {
+                0 => break,
+                read => len += read,
+            }
+        }
+        #[derive(AnchorDeserialize)]
+        struct Fields(Pubkey, u64, u64, u64, u64);
+        let Fields(
+            rewarded_publisher,
+            update_reward,
+            reimbursement_cap,
+            read_fee,
+            subscription_daily_fee,
+        ) = Fields::deserialize(&mut &bytes[..])?;
+        Ok(ConfigExtension {
+            rewarded_publisher,
+            update_reward,
+            reimbursement_cap,
+            read_fee,
+            subscription_daily_fee,
+        })
+    }
+}
+
+/// Program owned account the publishers are reimbursed from, funded by anyone with
+/// `fund_treasury`
+#[account]
+#[derive(Debug, Default)]
+pub struct Treasury {
+    pub total_reimbursed: u64,
+}
+
+impl Treasury {
+    pub const LEN: usize = 8 + size_of::<u64>();
 }
 
+/// Lets the subscriber read prices without paying the read fee until the subscription expires
 #[account]
+#[derive(Debug, Default)]
+pub struct Subscription {
+    pub subscriber: Pubkey,
+    pub expires_at: i64,
+}
+
+impl Subscription {
+    pub const LEN: usize = 8 + size_of::<Pubkey>() + size_of::<i64>();
+}
+
+/// The layout matches the borsh encoding of the accounts created before it became zero copy, so
+/// consumers can read a price by casting the account bytes after the discriminator
+#[account(zero_copy)]
 #[derive(Default, Debug)]
 pub struct LatestUpdate {
     pub data_key: [u8; 32],
     pub data: [u8; 32],
     pub data_timestamp: u64,
+    pub confidence: [u8; 32],
+    pub sources: u16,
+    /// The feed metadata set by the admin, it isn't touched by the updates
+    pub paused: u8,
+    pub padding: u8,
+    /// The expected interval between the updates in seconds, zero when it isn't expected
+    pub heartbeat: u32,
+}
+
+impl LatestUpdate {
+    /// Accounts created before the confidence was added are shorter and get reallocated to this
+    /// length on the next update or by `migrate_latest_update`
+    pub const LEN: usize = 8 + size_of::<LatestUpdate>();
+
+    pub fn status(&self, now: i64) -> FeedStatus {
+        let age = (now.max(0) as u64).saturating_sub(self.data_timestamp);
+        if self.paused != 0 {
+            FeedStatus::Paused
+        } else if self.heartbeat != 0 && age > self.heartbeat as u64 {
+            FeedStatus::Stale
+        } else {
+            FeedStatus::Fresh
+        }
+    }
+}
+
+impl From<ConfidenceDataFeed> for LatestUpdate {
+    fn from(data_feed: ConfidenceDataFeed) -> Self {
+        LatestUpdate {
+            data_key: data_feed.data_key,
+            data: data_feed.data,
+            data_timestamp: data_feed.timestamp,
+            confidence: data_feed.confidence,
+            sources: data_feed.sources,
+            ..Default::default()
+        }
+    }
 }
 
 impl TryFrom<DataFeed> for LatestUpdate {
@@ -146,8 +468,78 @@ impl TryFrom<DataFeed> for LatestUpdate {
             data: [0; 32],
             data_timestamp: data_feed.timestamp,
             data_key: data_feed.data_key,
+            ..Default::default()
         };
         latest_update.data.copy_from_slice(&data_feed.data[..32]);
         Ok(latest_update)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The size of the configs created before the extension
+    const LEGACY_CONFIG_LEN: usize = 136;
+
+    fn config() -> Config {
+        Config {
+            admin: Pubkey::new_unique(),
+            protocol_id: [7u8; 32],
+            signature_scheme: SignatureScheme::DomainSeparated,
+            chain_id: 5,
+            extension: ConfigExtension {
+                rewarded_publisher: Pubkey::new_unique(),
+                update_reward: 1,
+                reimbursement_cap: 2,
+                read_fee: 3,
+                subscription_daily_fee: 4,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn serialize(config: &Config) -> Vec<u8> {
+        let mut data = vec![];
+        config.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn reads_config_with_extension() {
+        let expected = config();
+        let mut data = serialize(&expected);
+        data.resize(Config::LEN, 0);
+
+        let config = Config::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(config.admin, expected.admin);
+        assert_eq!(config.chain_id, expected.chain_id);
+        assert_eq!(config.extension.rewarded_publisher, expected.extension.rewarded_publisher);
+        assert_eq!(config.extension.update_reward, 1);
+        assert_eq!(config.extension.reimbursement_cap, 2);
+        assert_eq!(config.extension.read_fee, 3);
+        assert_eq!(config.extension.subscription_daily_fee, 4);
+    }
+
+    #[test]
+    fn reads_legacy_config_with_zeroed_extension() {
+        let expected = config();
+        let mut data = serialize(&expected);
+        data.truncate(data.len() - ConfigExtension::LEN);
+        data.resize(LEGACY_CONFIG_LEN, 0);
+
+        let config = Config::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(config.admin, expected.admin);
+        assert_eq!(config.protocol_id, expected.protocol_id);
+        assert_eq!(config.signature_scheme, expected.signature_scheme);
+        assert_eq!(config.chain_id, expected.chain_id);
+        assert_eq!(config.extension.rewarded_publisher, Pubkey::default());
+        assert_eq!(config.extension.update_reward, 0);
+        assert_eq!(config.extension.read_fee, 0);
+    }
+
+    #[test]
+    fn config_fits_its_length() {
+        assert!(serialize(&config()).len() <= Config::LEN);
+    }
+}
@@ -12,4 +12,28 @@ pub enum CustomError {
     MerkleProofNotVerified,
     #[msg("Inconsistent data")]
     InconsistentData,
+    #[msg("Read fee is not paid and no valid subscription is provided")]
+    ReadFeeNotPaid,
+    #[msg("Subscriptions are disabled")]
+    SubscriptionsDisabled,
+    #[msg("Insufficient treasury funds")]
+    InsufficientTreasuryFunds,
+    #[msg("Data key must be a non empty printable ASCII name of up to 32 bytes")]
+    InvalidDataKey,
+    #[msg("Photon account is not owned by the photon program")]
+    InvalidPhotonAccountOwner,
+    #[msg("Photon account discriminator mismatch")]
+    InvalidPhotonAccountDiscriminator,
+    #[msg("Photon account is smaller than its layout")]
+    InvalidPhotonAccountSize,
+    #[msg("Signature r and s must be 32 bytes each")]
+    InvalidSignatureLength,
+    #[msg("Signature v must be one of 0, 1, 27 or 28")]
+    InvalidSignatureRecoveryId,
+    #[msg("Signature s must be in the lower half of the curve order")]
+    SignatureHighS,
+    #[msg("Latest update account is created before the current layout and must be migrated")]
+    AccountNotMigrated,
+    #[msg("Config is created before its extension and must be migrated")]
+    ConfigNotMigrated,
 }
@@ -36,7 +36,7 @@ impl core::hash::Hasher for PlaceholderHasher {
     }
 }
 
-pub(super) fn verify(proof: Vec<[u8; 32]>, root: [u8; 32], leaf: [u8; 32]) -> bool {
+pub fn verify(proof: Vec<[u8; 32]>, root: [u8; 32], leaf: [u8; 32]) -> bool {
     root == process_proof(proof, leaf)
 }
 
@@ -44,7 +44,8 @@ fn process_proof(proof: Vec<[u8; 32]>, leaf: [u8; 32]) -> [u8; 32] {
     proof.into_iter().fold(leaf, commutative_keccak256)
 }
 
-fn commutative_keccak256(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+/// Hashes the pair of nodes in the sorted order as OpenZeppelin `Hashes.commutativeKeccak256`
+pub fn commutative_keccak256(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
     if a < b {
         efficient_keccak256(a, b)
     } else {
@@ -61,3 +62,155 @@ fn efficient_keccak256(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
     hash.copy_from_slice(&result[..]);
     hash
 }
+
+/// Verifies multiple leaves against the root with a single deduplicated proof, the same way as
+/// OpenZeppelin `MerkleProof.multiProofVerify` does.
+pub fn multi_proof_verify(
+    proof: &[Hash],
+    proof_flags: &[bool],
+    root: Hash,
+    leaves: &[Hash],
+) -> bool {
+    process_multi_proof(proof, proof_flags, leaves) == Some(root)
+}
+
+fn process_multi_proof(proof: &[Hash], proof_flags: &[bool], leaves: &[Hash]) -> Option<Hash> {
+    let total_hashes = proof_flags.len();
+    if leaves.len() + proof.len() != total_hashes + 1 {
+        return None;
+    }
+
+    let mut hashes: Vec<Hash> = Vec::with_capacity(total_hashes);
+    let (mut leaf_pos, mut hash_pos, mut proof_pos) = (0, 0, 0);
+    for &flag in proof_flags {
+        let a = next_multi_proof_node(leaves, &hashes, &mut leaf_pos, &mut hash_pos)?;
+        let b = if flag {
+            next_multi_proof_node(leaves, &hashes, &mut leaf_pos, &mut hash_pos)?
+        } else {
+            proof_pos += 1;
+            *proof.get(proof_pos - 1)?
+        };
+        hashes.push(commutative_keccak256(a, b));
+    }
+
+    if total_hashes > 0 {
+        if proof_pos != proof.len() {
+            return None;
+        }
+        hashes.last().copied()
+    } else if let Some(leaf) = leaves.first() {
+        Some(*leaf)
+    } else {
+        proof.first().copied()
+    }
+}
+
+fn next_multi_proof_node(
+    leaves: &[Hash],
+    hashes: &[Hash],
+    leaf_pos: &mut usize,
+    hash_pos: &mut usize,
+) -> Option<Hash> {
+    if *leaf_pos < leaves.len() {
+        *leaf_pos += 1;
+        Some(leaves[*leaf_pos - 1])
+    } else {
+        *hash_pos += 1;
+        hashes.get(*hash_pos - 1).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data_feed_leaf, test_utils::multiple_update_message};
+
+    /// The tree of the multiple price update test: ((NGL, BTC), (ETH, x)), returns the root, the
+    /// leaves of NGL, BTC, ETH and the other node of the ETH subtree
+    fn tree() -> (Hash, [Hash; 3], Hash) {
+        let message = multiple_update_message();
+        let leaf = |index: usize| {
+            let data_feed = &message.data_feeds[index];
+            data_feed_leaf(data_feed.timestamp, &data_feed.data_key, &data_feed.data)
+        };
+        let (ngl, eth, btc) = (leaf(0), leaf(1), leaf(2));
+        // the ETH proof starts with its sibling
+        let x = message.data_feeds[1].merkle_proof[0];
+        (message.merkle_root, [ngl, btc, eth], x)
+    }
+
+    #[test]
+    fn verifies_single_proofs() {
+        let (root, [ngl, btc, eth], x) = tree();
+        let eth_subtree = commutative_keccak256(eth, x);
+        assert!(verify(vec![btc, eth_subtree], root, ngl));
+        assert!(verify(vec![ngl, eth_subtree], root, btc));
+        assert!(verify(vec![x, commutative_keccak256(ngl, btc)], root, eth));
+    }
+
+    #[test]
+    fn verifies_multi_proof_of_three_leaves() {
+        let (root, [ngl, btc, eth], x) = tree();
+        assert!(multi_proof_verify(&[x], &[true, false, true], root, &[ngl, btc, eth]));
+    }
+
+    #[test]
+    fn verifies_multi_proof_of_two_leaves() {
+        let (root, [ngl, btc, eth], x) = tree();
+        assert!(multi_proof_verify(&[btc, x], &[false, false, true], root, &[ngl, eth]));
+        let ngl_subtree = commutative_keccak256(ngl, btc);
+        assert!(multi_proof_verify(&[x, ngl_subtree], &[false, false], root, &[eth]));
+    }
+
+    #[test]
+    fn verifies_multi_proof_without_hashes() {
+        let (root, [ngl, btc, _], _) = tree();
+        assert!(multi_proof_verify(&[], &[], root, &[root]));
+        assert!(multi_proof_verify(&[root], &[], root, &[]));
+        assert!(!multi_proof_verify(&[], &[], root, &[ngl]));
+        assert!(!multi_proof_verify(&[], &[], root, &[]));
+        assert!(!multi_proof_verify(&[btc], &[], root, &[ngl]));
+    }
+
+    #[test]
+    fn rejects_flipped_proof_flag() {
+        let (root, [ngl, btc, eth], x) = tree();
+        for i in 0..3 {
+            let mut flags = [true, false, true];
+            flags[i] = !flags[i];
+            assert!(!multi_proof_verify(&[x], &flags, root, &[ngl, btc, eth]));
+        }
+    }
+
+    #[test]
+    fn rejects_extra_proof_element() {
+        let (root, [ngl, btc, eth], x) = tree();
+        assert!(!multi_proof_verify(&[x, x], &[true, false, true], root, &[ngl, btc, eth]));
+        // The counts add up with an extra flag, but the proof is still not consumed in the order
+        assert!(!multi_proof_verify(&[x, x], &[true, false, true, false], root, &[ngl, btc, eth]));
+    }
+
+    #[test]
+    fn rejects_short_proof() {
+        let (root, [ngl, _, eth], x) = tree();
+        assert!(!multi_proof_verify(&[x], &[false, false, true], root, &[ngl, eth]));
+        assert!(!multi_proof_verify(&[x], &[false, true], root, &[ngl, eth]));
+        assert!(!multi_proof_verify(&[], &[false, true], root, &[ngl, eth]));
+    }
+
+    #[test]
+    fn rejects_mismatched_counts() {
+        let (root, [ngl, btc, eth], x) = tree();
+        assert!(!multi_proof_verify(&[x], &[true, false], root, &[ngl, btc, eth]));
+        assert!(!multi_proof_verify(&[x], &[true, false, true, true], root, &[ngl, btc, eth]));
+        assert!(!multi_proof_verify(&[x], &[true, false, true], root, &[ngl, btc]));
+    }
+
+    #[test]
+    fn rejects_leaves_in_wrong_order() {
+        let (root, [ngl, btc, eth], x) = tree();
+        assert!(!multi_proof_verify(&[x], &[true, false, true], root, &[eth, ngl, btc]));
+        assert!(!multi_proof_verify(&[x], &[true, false, true], root, &[ngl, eth, btc]));
+        assert!(!multi_proof_verify(&[btc, x], &[false, false, true], root, &[eth, ngl]));
+    }
+}
@@ -1,18 +1,28 @@
 pub mod data;
 mod error;
-mod hash;
+pub mod hash;
+pub mod photon;
+pub mod signing;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 mod utils;
 
 use anchor_lang::{
     prelude::*,
-    solana_program::{program::invoke_signed, system_instruction},
+    solana_program::{
+        program::{invoke, invoke_signed},
+        system_instruction,
+    },
+    Discriminator,
 };
 use ethabi::Token;
 use std::mem::size_of;
 
 use data::{
-    Config, DataFeed, LastPriceMessage, LatestUpdate, MultipleUpdateMessage, ProtocolInfo,
-    TransmitterSignature,
+    ConfidenceDataFeed, ConfidenceUpdateMessage, Config, DataFeed, DataKey, LastPriceMessage,
+    LastPriceMessageV2, LatestUpdate, MultiProofUpdateMessage, MultipleUpdateMessage,
+    MultipleUpdateMessageV2, ProtocolInfo, SignatureScheme, Subscription, TransmitterSignatureV2,
+    Treasury, UpdateStatus,
 };
 
 use crate::{error::CustomError, hash::keccak256};
@@ -24,10 +34,12 @@ pub type EthAddress = [u8; 20];
 pub mod hashes {}
 
 #[cfg(not(feature = "mainnet"))]
-const UDF_PROTOCOL_ID: &[u8] = b"universal-data-feeds3\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+pub const UDF_PROTOCOL_ID: &[u8] =
+    b"universal-data-feeds3\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
 
 #[cfg(feature = "mainnet")]
-const UDF_PROTOCOL_ID: &[u8] = b"universal-data-feeds\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+pub const UDF_PROTOCOL_ID: &[u8] =
+    b"universal-data-feeds\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
 
 #[program]
 pub mod udf_solana {
@@ -46,6 +58,7 @@ pub mod udf_solana {
     pub const MAX_EXECUTORS: usize = 20;
     pub const MAX_PROPOSERS: usize = 20;
     pub const RATE_DECIMALS: u64 = 10000;
+    pub const SECONDS_PER_DAY: i64 = 86400;
 
     pub fn initialize(ctx: Context<Initialize>, protocol_id: [u8; 32]) -> Result<()> {
         ctx.accounts.config.admin = ctx.accounts.admin.key();
@@ -54,41 +67,385 @@ pub mod udf_solana {
         Ok(())
     }
 
+    pub fn set_signature_scheme(
+        ctx: Context<UpdateConfig>,
+        signature_scheme: SignatureScheme,
+        chain_id: u128,
+    ) -> Result<()> {
+        ctx.accounts.config.signature_scheme = signature_scheme;
+        ctx.accounts.config.chain_id = chain_id;
+        msg!("Signature scheme set: {}, chain id: {}", signature_scheme, chain_id);
+        Ok(())
+    }
+
+    /// Sets the publisher reimbursed from the treasury along with its rewards. The updates are
+    /// accepted from anyone with the transmitter signatures, only the registered publisher is
+    /// rewarded so that copying its transactions doesn't drain the treasury
+    pub fn set_publisher_rewards(
+        ctx: Context<UpdateConfig>,
+        rewarded_publisher: Pubkey,
+        update_reward: u64,
+        reimbursement_cap: u64,
+    ) -> Result<()> {
+        let extension = &mut ctx.accounts.config.extension;
+        extension.rewarded_publisher = rewarded_publisher;
+        extension.update_reward = update_reward;
+        extension.reimbursement_cap = reimbursement_cap;
+        msg!(
+            "Publisher rewards set, publisher: {}, update reward: {}, reimbursement cap: {}",
+            rewarded_publisher,
+            update_reward,
+            reimbursement_cap
+        );
+        Ok(())
+    }
+
+    /// Reallocates the config created before its extension. Such a config is still read with the
+    /// extension zeroed, but it can't be written by the admin instructions before the migration
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config_info = ctx.accounts.config.to_account_info();
+        {
+            let data = config_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 + size_of::<Pubkey>() && data[..8] == Config::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            let admin = Pubkey::try_from(&data[8..8 + size_of::<Pubkey>()])
+                .expect("Expected admin to be 32 bytes");
+            require_keys_eq!(admin, ctx.accounts.admin.key(), CustomError::IsNotAdmin);
+        }
+        if config_info.data_len() >= Config::LEN {
+            msg!("Config is already migrated");
+            return Ok(());
+        }
+        realloc_account(
+            &ctx.accounts.admin.to_account_info(),
+            &config_info,
+            &ctx.accounts.system_program.to_account_info(),
+            Config::LEN,
+        )
+    }
+
+    /// Transfers lamports to the treasury publishers are reimbursed from, creating it if needed
+    pub fn fund_treasury(ctx: Context<FundTreasury>, amount: u64) -> Result<()> {
+        invoke(
+            &system_instruction::transfer(
+                &ctx.accounts.funder.key(),
+                &ctx.accounts.treasury.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.funder.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+        msg!("Treasury funded with: {} lamports", amount);
+        Ok(())
+    }
+
     pub fn get_last_price<'info>(
-        ctx: Context<'_, '_, '_, 'info, UpdateAssets<'info>>,
+        ctx: Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
         last_price_message: LastPriceMessage,
     ) -> Result<[u8; 32]> {
+        get_last_price_v2(ctx, LastPriceMessageV2::try_from(last_price_message)?)
+    }
+
+    /// The same as `get_last_price` with fixed size signatures in the message
+    pub fn get_last_price_v2<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
+        last_price_message: LastPriceMessageV2,
+    ) -> Result<[u8; 32]> {
+        if !is_consensus_reached(
+            last_price_message.merkle_root,
+            &last_price_message.signatures,
+            &ctx.accounts.config,
+            &ctx.accounts.protocol_info,
+        )? {
+            return Err(Error::from(CustomError::ConsensusNotReached));
+        }
+        let data = last_price_message.data_feed.data;
+        update_asset(&ctx, last_price_message.data_feed, last_price_message.merkle_root)?;
+        Ok(data)
+    }
+
+    /// Verifies the signed price without storing it and returns the verified `(data, timestamp)`,
+    /// so the caller neither passes nor pays for the latest update account
+    pub fn verify_price(
+        ctx: Context<VerifyPrice>,
+        last_price_message: LastPriceMessage,
+    ) -> Result<()> {
+        verify_price_v2(ctx, LastPriceMessageV2::try_from(last_price_message)?)
+    }
+
+    /// The same as `verify_price` with fixed size signatures in the message
+    pub fn verify_price_v2(
+        ctx: Context<VerifyPrice>,
+        last_price_message: LastPriceMessageV2,
+    ) -> Result<()> {
         if !is_consensus_reached(
             last_price_message.merkle_root,
-            last_price_message.signatures,
+            &last_price_message.signatures,
+            &ctx.accounts.config,
             &ctx.accounts.protocol_info,
         )? {
             return Err(Error::from(CustomError::ConsensusNotReached));
         }
-        update_asset(&ctx, last_price_message.data_feed, last_price_message.merkle_root)
+        let data_feed = last_price_message.data_feed;
+        if !verify_data_feed(&data_feed, last_price_message.merkle_root) {
+            return Err(Error::from(CustomError::MerkleProofNotVerified));
+        }
+
+        let return_data = (data_feed.data, data_feed.timestamp)
+            .try_to_vec()
+            .expect("Expected return_data to be serialized with borsh");
+        set_return_data(&return_data);
+        Ok(())
     }
 
     pub fn update_multiple_assets<'info>(
-        ctx: Context<'_, '_, '_, 'info, UpdateAssets<'info>>,
+        ctx: Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
         multiple_update_message: MultipleUpdateMessage,
-    ) -> Result<()> {
+    ) -> Result<Vec<UpdateStatus>> {
+        update_multiple_assets_v2(ctx, MultipleUpdateMessageV2::try_from(multiple_update_message)?)
+    }
+
+    /// The same as `update_multiple_assets` with fixed size signatures in the message
+    pub fn update_multiple_assets_v2<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
+        multiple_update_message: MultipleUpdateMessageV2,
+    ) -> Result<Vec<UpdateStatus>> {
         if !is_consensus_reached(
             multiple_update_message.merkle_root,
-            multiple_update_message.signatures,
+            &multiple_update_message.signatures,
+            &ctx.accounts.config,
+            &ctx.accounts.protocol_info,
+        )? {
+            return Err(Error::from(CustomError::ConsensusNotReached));
+        }
+
+        let update_statuses = multiple_update_message
+            .data_feeds
+            .into_iter()
+            .map(|data_feed| update_asset(&ctx, data_feed, multiple_update_message.merkle_root))
+            .collect::<Result<Vec<UpdateStatus>>>()?;
+        reimburse_publisher(&ctx, &update_statuses)?;
+        Ok(update_statuses)
+    }
+
+    pub fn update_multiple_assets_multiproof<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
+        multi_proof_update_message: MultiProofUpdateMessage,
+    ) -> Result<Vec<UpdateStatus>> {
+        if !is_consensus_reached(
+            multi_proof_update_message.merkle_root,
+            &multi_proof_update_message.signatures,
+            &ctx.accounts.config,
+            &ctx.accounts.protocol_info,
+        )? {
+            return Err(Error::from(CustomError::ConsensusNotReached));
+        }
+
+        let leaves: Vec<[u8; 32]> = multi_proof_update_message
+            .data_feeds
+            .iter()
+            .map(|leaf| data_feed_leaf(leaf.timestamp, &leaf.data_key, &leaf.data))
+            .collect();
+        if !hash::multi_proof_verify(
+            &multi_proof_update_message.proof,
+            &multi_proof_update_message.proof_flags,
+            multi_proof_update_message.merkle_root,
+            &leaves,
+        ) {
+            return Err(Error::from(CustomError::MerkleProofNotVerified));
+        }
+
+        let update_statuses = multi_proof_update_message
+            .data_feeds
+            .iter()
+            .map(|leaf| {
+                let latest_update = LatestUpdate {
+                    data_key: leaf.data_key,
+                    data: leaf.data,
+                    data_timestamp: leaf.timestamp,
+                    ..Default::default()
+                };
+                store_data_feed(&ctx, latest_update)
+            })
+            .collect::<Result<Vec<UpdateStatus>>>()?;
+        reimburse_publisher(&ctx, &update_statuses)?;
+        Ok(update_statuses)
+    }
+
+    pub fn update_multiple_assets_with_confidence<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
+        confidence_update_message: ConfidenceUpdateMessage,
+    ) -> Result<Vec<UpdateStatus>> {
+        if !is_consensus_reached(
+            confidence_update_message.merkle_root,
+            &confidence_update_message.signatures,
+            &ctx.accounts.config,
             &ctx.accounts.protocol_info,
         )? {
             return Err(Error::from(CustomError::ConsensusNotReached));
         }
 
-        for data_feed in multiple_update_message.data_feeds {
-            update_asset(&ctx, data_feed, multiple_update_message.merkle_root)?;
+        let update_statuses = confidence_update_message
+            .data_feeds
+            .into_iter()
+            .map(|data_feed| {
+                if !verify_confidence_data_feed(&data_feed, confidence_update_message.merkle_root) {
+                    return Err(Error::from(CustomError::MerkleProofNotVerified));
+                }
+                store_data_feed(&ctx, LatestUpdate::from(data_feed))
+            })
+            .collect::<Result<Vec<UpdateStatus>>>()?;
+        reimburse_publisher(&ctx, &update_statuses)?;
+        Ok(update_statuses)
+    }
+
+    /// Reallocates a latest update account created before the current layout, so it can be read
+    /// before its next update. Anyone can pay for the migration
+    pub fn migrate_latest_update(
+        ctx: Context<MigrateLatestUpdate>,
+        _data_key: [u8; 32],
+    ) -> Result<()> {
+        let latest_update_info = ctx.accounts.latest_update.to_account_info();
+        if latest_update_info.data_len() >= LatestUpdate::LEN {
+            msg!("Latest update account is already migrated: {}", latest_update_info.key);
+            return Ok(());
         }
+        realloc_account(
+            &ctx.accounts.payer.to_account_info(),
+            &latest_update_info,
+            &ctx.accounts.system_program.to_account_info(),
+            LatestUpdate::LEN,
+        )
+    }
+
+    pub fn set_read_fees(
+        ctx: Context<UpdateConfig>,
+        read_fee: u64,
+        subscription_daily_fee: u64,
+    ) -> Result<()> {
+        ctx.accounts.config.extension.read_fee = read_fee;
+        ctx.accounts.config.extension.subscription_daily_fee = subscription_daily_fee;
+        msg!(
+            "Read fees set, read fee: {}, subscription daily fee: {}",
+            read_fee,
+            subscription_daily_fee
+        );
+        Ok(())
+    }
+
+    /// Pays for the subscription of the subscriber, extending it by the given number of days
+    pub fn subscribe(ctx: Context<Subscribe>, days: u32) -> Result<()> {
+        let daily_fee = ctx.accounts.config.extension.subscription_daily_fee;
+        require!(daily_fee > 0, CustomError::SubscriptionsDisabled);
+        invoke(
+            &system_instruction::transfer(
+                &ctx.accounts.subscriber.key(),
+                &ctx.accounts.treasury.key(),
+                daily_fee.saturating_mul(days as u64),
+            ),
+            &[
+                ctx.accounts.subscriber.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.subscriber = ctx.accounts.subscriber.key();
+        subscription.expires_at =
+            subscription.expires_at.max(now).saturating_add(days as i64 * SECONDS_PER_DAY);
+        msg!("Subscription: {} expires at: {}", subscription.subscriber, subscription.expires_at);
+        Ok(())
+    }
 
+    /// Withdraws the accumulated fees from the treasury, keeping it rent exempt
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let available = treasury_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(treasury_info.data_len()));
+        require!(amount <= available, CustomError::InsufficientTreasuryFunds);
+        **treasury_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+        msg!("Treasury withdrawn: {} lamports to: {}", amount, ctx.accounts.recipient.key());
         Ok(())
     }
 
-    pub fn last_price(ctx: Context<'_, '_, '_, '_, LastPrice>, _asset: String) -> Result<()> {
-        let return_data = (ctx.accounts.last_price.data, ctx.accounts.last_price.data_timestamp)
+    /// Sets the expected heartbeat interval of the feed in seconds and pauses or resumes it, the
+    /// reads report the feed status based on them
+    pub fn set_feed_metadata(
+        ctx: Context<UpdateFeedMetadata>,
+        _data_key: [u8; 32],
+        heartbeat: u32,
+        paused: bool,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.latest_update.to_account_info().data_len() >= LatestUpdate::LEN,
+            CustomError::AccountNotMigrated
+        );
+        let mut latest_update = ctx.accounts.latest_update.load_mut()?;
+        latest_update.heartbeat = heartbeat;
+        latest_update.paused = paused as u8;
+        let data_key = DataKey::from(latest_update.data_key);
+        msg!("Feed metadata set: {}, heartbeat: {}, paused: {}", data_key, heartbeat, paused);
+        Ok(())
+    }
+
+    /// Returns the stored `(data, timestamp)` of the asset, accounts created before the current
+    /// layout have to be migrated first. When the read fee is enabled, the payer is charged the
+    /// fee unless it passes its unexpired subscription
+    pub fn last_price(ctx: Context<LastPrice>, _data_key: DataKey) -> Result<()> {
+        let last_price = read_last_price(ctx.accounts)?;
+        let return_data = (last_price.data, last_price.data_timestamp)
+            .try_to_vec()
+            .expect("Expected return_data to be serialized with borsh");
+        set_return_data(&return_data);
+        Ok(())
+    }
+
+    /// Returns the stored `(data, timestamp, confidence, sources)` of the asset
+    pub fn last_price_with_confidence(ctx: Context<LastPrice>, _data_key: DataKey) -> Result<()> {
+        let last_price = read_last_price(ctx.accounts)?;
+        let return_data =
+            (last_price.data, last_price.data_timestamp, last_price.confidence, last_price.sources)
+                .try_to_vec()
+                .expect("Expected return_data to be serialized with borsh");
+        set_return_data(&return_data);
+        Ok(())
+    }
+
+    /// The same as `last_price` with the status of the feed appended, `(data, timestamp, status)`
+    pub fn last_price_v2(ctx: Context<LastPrice>, _data_key: DataKey) -> Result<()> {
+        let last_price = read_last_price(ctx.accounts)?;
+        let status = last_price.status(Clock::get()?.unix_timestamp);
+        let return_data = (last_price.data, last_price.data_timestamp, status)
+            .try_to_vec()
+            .expect("Expected return_data to be serialized with borsh");
+        set_return_data(&return_data);
+        Ok(())
+    }
+
+    /// The same as `last_price_with_confidence` with the status of the feed appended,
+    /// `(data, timestamp, confidence, sources, status)`
+    pub fn last_price_with_confidence_v2(
+        ctx: Context<LastPrice>,
+        _data_key: DataKey,
+    ) -> Result<()> {
+        let last_price = read_last_price(ctx.accounts)?;
+        let status = last_price.status(Clock::get()?.unix_timestamp);
+        let return_data = (
+            last_price.data,
+            last_price.data_timestamp,
+            last_price.confidence,
+            last_price.sources,
+            status,
+        )
             .try_to_vec()
             .expect("Expected return_data to be serialized with borsh");
         set_return_data(&return_data);
@@ -97,28 +454,52 @@ pub mod udf_solana {
 }
 
 #[derive(Accounts)]
-#[instruction(asset: String)]
+#[instruction(data_key: DataKey)]
 pub struct LastPrice<'info> {
-    #[account(seeds = [ROOT, b"LAST_UPDATE", UDF_PROTOCOL_ID, asset.as_bytes()], bump)]
-    last_price: Account<'info, LatestUpdate>,
+    #[account(seeds = [ROOT, b"LAST_UPDATE", UDF_PROTOCOL_ID, &data_key.key], bump)]
+    last_price: AccountLoader<'info, LatestUpdate>,
+    #[account(seeds = [ROOT, b"CONFIG"], bump)]
+    config: Box<Account<'info, Config>>,
+    /// Pays the read fee or holds the subscription, only needed while the read fee is enabled
+    #[account(mut)]
+    payer: Option<Signer<'info>>,
+    #[account(seeds = [ROOT, b"SUBSCRIPTION", subscription.subscriber.as_ref()], bump)]
+    subscription: Option<Account<'info, Subscription>>,
+    #[account(mut, seeds = [ROOT, b"TREASURY"], bump)]
+    treasury: Option<Account<'info, Treasury>>,
+    system_program: Option<Program<'info, System>>,
 }
 
 pub fn update_asset<'info>(
-    ctx: &Context<'_, '_, '_, 'info, UpdateAssets<'info>>,
+    ctx: &Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
     data_feed: DataFeed,
     merkle_root: [u8; 32],
-) -> Result<[u8; 32]> {
+) -> Result<UpdateStatus> {
     require!(data_feed.data.len() == 32, CustomError::InconsistentData);
 
     if !verify_data_feed(&data_feed, merkle_root) {
         return Err(Error::from(CustomError::MerkleProofNotVerified));
     };
+    let latest_update = LatestUpdate {
+        data_key: data_feed.data_key,
+        data: data_feed.data,
+        data_timestamp: data_feed.timestamp,
+        ..Default::default()
+    };
+    store_data_feed(ctx, latest_update)
+}
+
+fn store_data_feed<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
+    update: LatestUpdate,
+) -> Result<UpdateStatus> {
+    let data_key = update.data_key;
     let (latest_update_pda, latest_update_bump) = Pubkey::find_program_address(
         &[
             ROOT,
             b"LAST_UPDATE",
             &ctx.accounts.config.protocol_id,
-            &data_feed.data_key,
+            &data_key,
         ],
         &ID,
     );
@@ -126,55 +507,93 @@ pub fn update_asset<'info>(
         .remaining_accounts
         .iter()
         .find(|account_info| account_info.key == &latest_update_pda)
-        .expect("Expected to be present")
-        .clone();
-
-    if **latest_update_info.lamports.borrow() == 0 {
-        alloc_latest_update_account(
-            ctx,
-            data_feed.data_key,
-            latest_update_bump,
-            latest_update_info.clone(),
+        .expect("Expected to be present");
+
+    let created = **latest_update_info.lamports.borrow() == 0;
+    if created {
+        alloc_latest_update_account(ctx, data_key, latest_update_bump, latest_update_info)?;
+        latest_update_info.try_borrow_mut_data()?[..8]
+            .copy_from_slice(&LatestUpdate::DISCRIMINATOR);
+    } else if latest_update_info.data_len() < LatestUpdate::LEN {
+        realloc_account(
+            &ctx.accounts.publisher.to_account_info(),
+            latest_update_info,
+            &ctx.accounts.system_program.to_account_info(),
+            LatestUpdate::LEN,
         )?;
-        LatestUpdate::default()
-            .try_serialize(&mut &mut latest_update_info.try_borrow_mut_data()?[..])?;
     };
 
-    let mut latest_update =
-        LatestUpdate::try_deserialize(&mut &latest_update_info.try_borrow_data()?[..])?;
+    let latest_update_loader = AccountLoader::<LatestUpdate>::try_from(latest_update_info)?;
+    let mut latest_update = latest_update_loader.load_mut()?;
 
     // If the timestamp of new update is older than the latest timestamp,
     // parse and ignore this update
-    if data_feed.timestamp <= latest_update.data_timestamp {
-        let data_key = String::from_utf8_lossy(&data_feed.data_key);
+    if update.data_timestamp <= latest_update.data_timestamp {
+        let data_key = DataKey::from(data_key);
         msg!("New update is older for the given key and is ignored: {}", data_key);
-        return Ok(data_feed.data);
+        return Ok(UpdateStatus::IgnoredOlder);
     }
-    latest_update.data.copy_from_slice(&data_feed.data[..32]);
-    latest_update.data_timestamp = data_feed.timestamp;
-    latest_update.data_key = data_feed.data_key;
+    latest_update.data_key = update.data_key;
+    latest_update.data = update.data;
+    latest_update.data_timestamp = update.data_timestamp;
+    latest_update.confidence = update.confidence;
+    latest_update.sources = update.sources;
+    Ok(if created {
+        UpdateStatus::Created
+    } else {
+        UpdateStatus::Updated
+    })
+}
+
+pub fn verify_data_feed(data_feed: &DataFeed, merkle_root: [u8; 32]) -> bool {
+    let leaf = data_feed_leaf(data_feed.timestamp, &data_feed.data_key, &data_feed.data);
+    hash::verify(data_feed.merkle_proof.clone(), merkle_root, leaf)
+}
 
-    latest_update.try_serialize(&mut &mut latest_update_info.try_borrow_mut_data()?[..])?;
-    Ok(data_feed.data)
+pub fn verify_confidence_data_feed(data_feed: &ConfidenceDataFeed, merkle_root: [u8; 32]) -> bool {
+    let leaf = confidence_data_feed_leaf(
+        data_feed.timestamp,
+        &data_feed.data_key,
+        &data_feed.data,
+        &data_feed.confidence,
+        data_feed.sources,
+    );
+    hash::verify(data_feed.merkle_proof.clone(), merkle_root, leaf)
 }
 
-fn verify_data_feed(data_feed: &DataFeed, merkle_root: [u8; 32]) -> bool {
+pub fn confidence_data_feed_leaf(
+    timestamp: u64,
+    data_key: &[u8; 32],
+    data: &[u8; 32],
+    confidence: &[u8; 32],
+    sources: u16,
+) -> [u8; 32] {
     let leaf = ethabi::encode(&[
-        Token::Uint(ethabi::Uint::from(data_feed.timestamp)),
-        Token::Bytes(data_feed.data.to_vec()),
-        Token::FixedBytes(data_feed.data_key.to_vec()),
+        Token::Uint(ethabi::Uint::from(timestamp)),
+        Token::Bytes(data.to_vec()),
+        Token::FixedBytes(data_key.to_vec()),
+        Token::Bytes(confidence.to_vec()),
+        Token::Uint(ethabi::Uint::from(sources)),
     ]);
-    let leaf = keccak256(keccak256(leaf));
-    hash::verify(data_feed.merkle_proof.clone(), merkle_root, leaf)
+    keccak256(keccak256(leaf))
+}
+
+pub fn data_feed_leaf(timestamp: u64, data_key: &[u8; 32], data: &[u8; 32]) -> [u8; 32] {
+    let leaf = ethabi::encode(&[
+        Token::Uint(ethabi::Uint::from(timestamp)),
+        Token::Bytes(data.to_vec()),
+        Token::FixedBytes(data_key.to_vec()),
+    ]);
+    keccak256(keccak256(leaf))
 }
 
 fn alloc_latest_update_account<'info>(
-    ctx: &Context<'_, '_, '_, 'info, UpdateAssets<'info>>,
+    ctx: &Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
     data_key: [u8; 32],
     latest_update_bump: u8,
-    latest_update_info: AccountInfo<'info>,
+    latest_update_info: &AccountInfo<'info>,
 ) -> Result<()> {
-    let space = size_of::<LatestUpdate>() + 8;
+    let space = LatestUpdate::LEN;
     let lamports = Rent::get()?.minimum_balance(space);
     let publisher_info = ctx.accounts.publisher.to_account_info();
     let system_program_info = ctx.accounts.system_program.to_account_info();
@@ -203,19 +622,129 @@ fn alloc_latest_update_account<'info>(
     Ok(())
 }
 
+fn realloc_account<'info>(
+    payer_info: &AccountInfo<'info>,
+    account_info: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    len: usize,
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(len).saturating_sub(account_info.lamports());
+    if lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, account_info.key, lamports),
+            &[
+                payer_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    account_info.realloc(len, true)?;
+    Ok(())
+}
+
+/// Lets the read through when the read fee is disabled or a valid subscription is signed by its
+/// subscriber, otherwise transfers the read fee from the signing fee payer to the treasury
+/// Loads the latest update of the price reads after charging the read fee
+fn read_last_price(accounts: &LastPrice) -> Result<LatestUpdate> {
+    require!(
+        accounts.last_price.to_account_info().data_len() >= LatestUpdate::LEN,
+        CustomError::AccountNotMigrated
+    );
+    charge_read_fee(accounts)?;
+    let last_price = accounts.last_price.load()?;
+    Ok(*last_price)
+}
+
+/// Charges the read fee of the config from the payer, unless the payer holds an unexpired
+/// subscription. The accounts are optional while the read fee is disabled
+fn charge_read_fee(accounts: &LastPrice) -> Result<()> {
+    let read_fee = accounts.config.extension.read_fee;
+    if read_fee == 0 {
+        return Ok(());
+    }
+
+    let payer = accounts.payer.as_ref().ok_or(CustomError::ReadFeeNotPaid)?;
+    if let Some(subscription) = &accounts.subscription {
+        require_keys_eq!(subscription.subscriber, payer.key(), CustomError::ReadFeeNotPaid);
+        if subscription.expires_at > Clock::get()?.unix_timestamp {
+            return Ok(());
+        }
+    }
+
+    let (Some(treasury), Some(system_program)) = (&accounts.treasury, &accounts.system_program)
+    else {
+        return Err(Error::from(CustomError::ReadFeeNotPaid));
+    };
+    invoke(
+        &system_instruction::transfer(&payer.key(), &treasury.key(), read_fee),
+        &[
+            payer.to_account_info(),
+            treasury.to_account_info(),
+            system_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Reimburses the registered publisher from the treasury for the rent of the created latest
+/// update accounts and rewards it for every stored data feed, up to the cap of the config. The
+/// treasury is expected among the remaining accounts, the publisher isn't reimbursed without it
+fn reimburse_publisher<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, UpdateAssets<'info>>,
+    update_statuses: &[UpdateStatus],
+) -> Result<()> {
+    if ctx.accounts.publisher.key() != ctx.accounts.config.extension.rewarded_publisher {
+        return Ok(());
+    }
+    let (treasury_pda, _) = Pubkey::find_program_address(&[ROOT, b"TREASURY"], &ID);
+    let treasury_info = match ctx
+        .remaining_accounts
+        .iter()
+        .find(|account_info| account_info.key == &treasury_pda && account_info.owner == &ID)
+    {
+        Some(treasury_info) => treasury_info,
+        None => return Ok(()),
+    };
+
+    let created =
+        update_statuses.iter().filter(|status| **status == UpdateStatus::Created).count() as u64;
+    let stored =
+        update_statuses.iter().filter(|status| **status != UpdateStatus::IgnoredOlder).count()
+            as u64;
+    let rent = Rent::get()?;
+    let available =
+        treasury_info.lamports().saturating_sub(rent.minimum_balance(treasury_info.data_len()));
+    let lamports = created
+        .saturating_mul(rent.minimum_balance(LatestUpdate::LEN))
+        .saturating_add(stored.saturating_mul(ctx.accounts.config.extension.update_reward))
+        .min(ctx.accounts.config.extension.reimbursement_cap)
+        .min(available);
+    if lamports == 0 {
+        return Ok(());
+    }
+
+    let mut treasury = Account::<Treasury>::try_from(treasury_info)?;
+    treasury.total_reimbursed = treasury.total_reimbursed.saturating_add(lamports);
+    treasury.exit(&ID)?;
+    **treasury_info.try_borrow_mut_lamports()? -= lamports;
+    **ctx.accounts.publisher.to_account_info().try_borrow_mut_lamports()? += lamports;
+    msg!("Publisher reimbursed with: {} lamports", lamports);
+    Ok(())
+}
+
 fn is_consensus_reached(
     merkle_root: [u8; 32],
-    signatures: Vec<TransmitterSignature>,
+    signatures: &[TransmitterSignatureV2],
+    config: &Config,
     protocol_info: &ProtocolInfo,
 ) -> Result<bool> {
-    let mut merkle_root_bytes = b"\x19Ethereum Signed Message:\n32".to_vec();
-    merkle_root_bytes.extend(merkle_root.to_vec());
-    let hash_to_recover_sig: [u8; 32] = keccak256(merkle_root_bytes);
+    let hash_to_recover_sig = signing::merkle_root_digest(config, &ID, &merkle_root);
     let allowed_transmitters = protocol_info.transmitters();
 
     let mut unique_signers = vec![];
     let mut consensus_reached = false;
-    for signature in &signatures {
+    for signature in signatures {
         let transmitter = utils::ecrecover(&hash_to_recover_sig, signature)?;
         if !allowed_transmitters.contains(&transmitter) || unique_signers.contains(&transmitter) {
             continue;
@@ -243,6 +772,19 @@ pub struct Initialize<'info> {
     system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(signer, constraint = admin.key() == config.admin @ CustomError::IsNotAdmin)]
+    admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ROOT, b"CONFIG"],
+        bump,
+        constraint = config.to_account_info().data_len() >= Config::LEN @ CustomError::ConfigNotMigrated
+    )]
+    config: Box<Account<'info, Config>>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateAssets<'info> {
     #[account(signer, mut)]
@@ -253,3 +795,81 @@ pub struct UpdateAssets<'info> {
     protocol_info: Box<Account<'info, ProtocolInfo>>,
     system_program: Program<'info, System>,
 }
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(signer, mut)]
+    admin: Signer<'info>,
+    /// CHECK: the config can't be deserialized before it is reallocated, the admin is checked by
+    /// the instruction
+    #[account(mut, owner = ID, seeds = [ROOT, b"CONFIG"], bump)]
+    config: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundTreasury<'info> {
+    #[account(signer, mut)]
+    funder: Signer<'info>,
+    #[account(init_if_needed, payer = funder, space = Treasury::LEN, seeds = [ROOT, b"TREASURY"], bump)]
+    treasury: Account<'info, Treasury>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(data_key: [u8; 32])]
+pub struct UpdateFeedMetadata<'info> {
+    #[account(signer, constraint = admin.key() == config.admin @ CustomError::IsNotAdmin)]
+    admin: Signer<'info>,
+    #[account(seeds = [ROOT, b"CONFIG"], bump)]
+    config: Box<Account<'info, Config>>,
+    #[account(mut, seeds = [ROOT, b"LAST_UPDATE", &config.protocol_id, &data_key], bump)]
+    latest_update: AccountLoader<'info, LatestUpdate>,
+}
+
+#[derive(Accounts)]
+pub struct Subscribe<'info> {
+    #[account(signer, mut)]
+    subscriber: Signer<'info>,
+    #[account(seeds = [ROOT, b"CONFIG"], bump)]
+    config: Box<Account<'info, Config>>,
+    #[account(init_if_needed, payer = subscriber, space = Subscription::LEN, seeds = [ROOT, b"SUBSCRIPTION", subscriber.key().as_ref()], bump)]
+    subscription: Account<'info, Subscription>,
+    #[account(init_if_needed, payer = subscriber, space = Treasury::LEN, seeds = [ROOT, b"TREASURY"], bump)]
+    treasury: Account<'info, Treasury>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(signer, constraint = admin.key() == config.admin @ CustomError::IsNotAdmin)]
+    admin: Signer<'info>,
+    #[account(seeds = [ROOT, b"CONFIG"], bump)]
+    config: Box<Account<'info, Config>>,
+    #[account(mut, seeds = [ROOT, b"TREASURY"], bump)]
+    treasury: Account<'info, Treasury>,
+    /// CHECK: any account can receive the withdrawn lamports
+    #[account(mut)]
+    recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyPrice<'info> {
+    #[account(seeds = [ROOT, b"CONFIG"], bump)]
+    config: Box<Account<'info, Config>>,
+    #[account(seeds = [PHOTON_ROOT, b"PROTOCOL", &config.protocol_id], bump, seeds::program = config.endpoint)]
+    protocol_info: Box<Account<'info, ProtocolInfo>>,
+}
+
+#[derive(Accounts)]
+#[instruction(data_key: [u8; 32])]
+pub struct MigrateLatestUpdate<'info> {
+    #[account(signer, mut)]
+    payer: Signer<'info>,
+    #[account(seeds = [ROOT, b"CONFIG"], bump)]
+    config: Box<Account<'info, Config>>,
+    /// CHECK: the account can't be deserialized before it is reallocated
+    #[account(mut, owner = ID, seeds = [ROOT, b"LAST_UPDATE", &config.protocol_id, &data_key], bump)]
+    latest_update: UncheckedAccount<'info>,
+    system_program: Program<'info, System>,
+}
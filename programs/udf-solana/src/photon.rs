@@ -0,0 +1,153 @@
+//! Read-only views of the photon cross chain messaging accounts the price oracle depends on. The
+//! layouts are copied from the photon program and validated on every read.
+
+use anchor_lang::{prelude::*, Discriminator};
+use std::io::Write;
+
+use crate::{
+    error::CustomError,
+    udf_solana::{MAX_EXECUTORS, MAX_PROPOSERS, MAX_TRANSMITTERS},
+    EthAddress,
+};
+
+/// The photon program the protocol info accounts belong to
+pub const PHOTON_PROGRAM: Pubkey = Pubkey::new_from_array([
+    12, 50, 145, 223, 16, 33, 233, 37, 119, 186, 206, 30, 187, 117, 189, 70, 23, 0, 141, 139, 21,
+    92, 169, 187, 124, 139, 89, 86, 127, 197, 95, 163,
+]);
+
+/// The discriminator of the protocol info account as it is defined in the photon program
+pub const PROTOCOL_INFO_DISCRIMINATOR: [u8; 8] = [40, 62, 222, 136, 36, 92, 1, 233];
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct ProtocolInfo {
+    is_init: bool,
+    pub consensus_target_rate: u64,
+    protocol_address: Pubkey,
+    // cannot use const with anchor
+    pub transmitters: Box<[EthAddress; 20]>,
+    executors: Box<[Pubkey; 20]>,
+    proposers: Box<[Pubkey; 20]>,
+}
+
+impl ProtocolInfo {
+    /// The discriminator along with the fields above, it's the size the photon program allocates
+    /// the protocol info with. Longer accounts are accepted, the trailing bytes aren't read
+    pub const LEN: usize =
+        8 + 1 + 8 + 32 + (20 * MAX_TRANSMITTERS) + (32 * MAX_EXECUTORS) + (32 * MAX_PROPOSERS);
+
+    /// Reads the protocol info from the account data, checking the owner along with the layout
+    pub fn try_from_account(owner: &Pubkey, mut data: &[u8]) -> Result<Self> {
+        require_keys_eq!(*owner, PHOTON_PROGRAM, CustomError::InvalidPhotonAccountOwner);
+        Self::try_deserialize(&mut data)
+    }
+
+    pub fn is_init(&self) -> bool {
+        self.is_init
+    }
+
+    pub fn protocol_address(&self) -> Pubkey {
+        self.protocol_address
+    }
+
+    pub fn transmitters(&self) -> Vec<EthAddress> {
+        self.transmitters.into_iter().take_while(|k| k != &EthAddress::default()).collect()
+    }
+
+    pub fn executors(&self) -> Vec<Pubkey> {
+        self.executors.into_iter().take_while(|k| k != &Pubkey::default()).collect()
+    }
+
+    pub fn proposers(&self) -> Vec<Pubkey> {
+        self.proposers.into_iter().take_while(|k| k != &Pubkey::default()).collect()
+    }
+}
+
+impl AccountSerialize for ProtocolInfo {
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        if writer.write_all(&PROTOCOL_INFO_DISCRIMINATOR).is_err() {
+            return Err(ErrorCode::AccountDidNotSerialize.into());
+        }
+        if AnchorSerialize::serialize(self, writer).is_err() {
+            return Err(ErrorCode::AccountDidNotSerialize.into());
+        }
+        Ok(())
+    }
+}
+
+impl AccountDeserialize for ProtocolInfo {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        require!(buf.len() >= Self::LEN, CustomError::InvalidPhotonAccountSize);
+        require!(
+            buf[..PROTOCOL_INFO_DISCRIMINATOR.len()] == PROTOCOL_INFO_DISCRIMINATOR,
+            CustomError::InvalidPhotonAccountDiscriminator
+        );
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data: &[u8] = &buf[PROTOCOL_INFO_DISCRIMINATOR.len()..];
+        AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl Discriminator for ProtocolInfo {
+    const DISCRIMINATOR: [u8; 8] = PROTOCOL_INFO_DISCRIMINATOR;
+}
+
+impl Owner for ProtocolInfo {
+    fn owner() -> Pubkey {
+        PHOTON_PROGRAM
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_data() -> Vec<u8> {
+        let protocol_info = ProtocolInfo {
+            is_init: true,
+            consensus_target_rate: 6000,
+            ..Default::default()
+        };
+        let mut data = Vec::new();
+        protocol_info.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn len_covers_the_serialized_account() {
+        assert_eq!(account_data().len(), ProtocolInfo::LEN);
+    }
+
+    #[test]
+    fn reads_accounts_of_at_least_len() {
+        let mut data = account_data();
+        data.extend_from_slice(&[0; 64]);
+        let protocol_info = ProtocolInfo::try_from_account(&PHOTON_PROGRAM, &data).unwrap();
+        assert!(protocol_info.is_init());
+        assert_eq!(protocol_info.consensus_target_rate, 6000);
+
+        assert_eq!(
+            ProtocolInfo::try_from_account(&PHOTON_PROGRAM, &data[..ProtocolInfo::LEN - 1])
+                .unwrap_err(),
+            CustomError::InvalidPhotonAccountSize.into()
+        );
+    }
+
+    #[test]
+    fn rejects_foreign_accounts() {
+        let mut data = account_data();
+        assert_eq!(
+            ProtocolInfo::try_from_account(&Pubkey::new_unique(), &data).unwrap_err(),
+            CustomError::InvalidPhotonAccountOwner.into()
+        );
+        data[0] ^= 1;
+        assert_eq!(
+            ProtocolInfo::try_from_account(&PHOTON_PROGRAM, &data).unwrap_err(),
+            CustomError::InvalidPhotonAccountDiscriminator.into()
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use anchor_lang::prelude::Pubkey;
+use ethabi::Token;
+
+use crate::{
+    data::{Config, SignatureScheme},
+    hash::{keccak256, Hash},
+};
+
+/// The version of the signing domain, it's to be increased whenever the signed data changes
+pub const SIGNING_DOMAIN_VERSION: u64 = 1;
+
+pub const DOMAIN_TYPE: &[u8] =
+    b"EIP712Domain(bytes32 protocolId,uint256 chainId,bytes32 programId,uint256 version)";
+pub const MERKLE_ROOT_TYPE: &[u8] = b"MerkleRoot(bytes32 merkleRoot)";
+
+/// Calculates the digest of the merkle root expected to be signed by transmitters for the
+/// signature scheme selected in the config
+pub fn merkle_root_digest(config: &Config, program_id: &Pubkey, merkle_root: &Hash) -> Hash {
+    match config.signature_scheme {
+        SignatureScheme::EthSignedMessage => eth_signed_message_digest(merkle_root),
+        SignatureScheme::DomainSeparated => {
+            let domain_separator =
+                domain_separator(&config.protocol_id, config.chain_id, program_id);
+            domain_separated_digest(&domain_separator, merkle_root)
+        }
+    }
+}
+
+pub fn eth_signed_message_digest(merkle_root: &Hash) -> Hash {
+    let mut merkle_root_bytes = b"\x19Ethereum Signed Message:\n32".to_vec();
+    merkle_root_bytes.extend(merkle_root.to_vec());
+    keccak256(merkle_root_bytes)
+}
+
+/// Binds signatures to the protocol, the target chain and the program deployment
+pub fn domain_separator(protocol_id: &[u8; 32], chain_id: u128, program_id: &Pubkey) -> Hash {
+    keccak256(ethabi::encode(&[
+        Token::FixedBytes(keccak256(DOMAIN_TYPE).to_vec()),
+        Token::FixedBytes(protocol_id.to_vec()),
+        Token::Uint(ethabi::Uint::from(chain_id)),
+        Token::FixedBytes(program_id.to_bytes().to_vec()),
+        Token::Uint(ethabi::Uint::from(SIGNING_DOMAIN_VERSION)),
+    ]))
+}
+
+/// The EIP-712 typed data digest of the merkle root within the given domain
+pub fn domain_separated_digest(domain_separator: &Hash, merkle_root: &Hash) -> Hash {
+    let struct_hash = keccak256(ethabi::encode(&[
+        Token::FixedBytes(keccak256(MERKLE_ROOT_TYPE).to_vec()),
+        Token::FixedBytes(merkle_root.to_vec()),
+    ]));
+    keccak256([&b"\x19\x01"[..], &domain_separator[..], &struct_hash[..]].concat())
+}
@@ -0,0 +1,85 @@
+//! The fixed vectors of the recorded price updates, shared by the unit tests of the program and
+//! the client SDK
+
+use crate::{
+    data::{DataFeed, MultipleUpdateMessageV2, TransmitterSignatureV2},
+    hash::Hash,
+};
+
+/// The merkle root of the single price update test
+pub const SINGLE_UPDATE_MERKLE_ROOT: &str =
+    "07963960682c8bf05845f099078d6839bf6f6d6d159f36adc32dcff465c71b56";
+
+/// The address of the first transmitter of the single price update test
+pub const SINGLE_UPDATE_TRANSMITTER: &str = "efcf6f62254f76d9ef9bc06c843eac97f0aa1723";
+
+/// The merkle root of the multiple price update test, the tree is ((NGL, BTC), (ETH, x))
+pub const MULTIPLE_UPDATE_MERKLE_ROOT: &str =
+    "b3dcf4e5fa1d50eda24c8fd2e35ec9fd2be13a4829bf2d41a240c96b28e1fece";
+
+pub fn bytes32(value: &str) -> Hash {
+    hex::decode(value).unwrap().try_into().unwrap()
+}
+
+/// The data key of the asset, zero padded the same as the publisher does
+pub fn data_key(asset: &str) -> [u8; 32] {
+    let mut data_key = [0u8; 32];
+    data_key[..asset.len()].copy_from_slice(asset.as_bytes());
+    data_key
+}
+
+/// The signature of the first transmitter of the single price update test
+pub fn single_update_signature() -> TransmitterSignatureV2 {
+    TransmitterSignatureV2 {
+        v: 28,
+        r: bytes32("d92028d242d7b7b054bfa9b5a9f336d4352b5b1da3cafd71288c14b3f9ae722c"),
+        s: bytes32("6053b190e6f39c7b08cc37b68ac1d8ecd9ab735cdade4cc898724760777da98a"),
+    }
+}
+
+fn data_feed(timestamp: u64, asset: &str, data: &str, merkle_proof: [&str; 2]) -> DataFeed {
+    DataFeed {
+        timestamp,
+        data_key: data_key(asset),
+        data: bytes32(data),
+        merkle_proof: merkle_proof.iter().map(|node| bytes32(node)).collect(),
+    }
+}
+
+/// The data feeds of NGL, ETH and BTC of the multiple price update test along with their proofs,
+/// the signatures are left out
+pub fn multiple_update_message() -> MultipleUpdateMessageV2 {
+    MultipleUpdateMessageV2 {
+        merkle_root: bytes32(MULTIPLE_UPDATE_MERKLE_ROOT),
+        data_feeds: vec![
+            data_feed(
+                1721923139,
+                "NGL/USD",
+                "000000000000000000000000000000000000000000000000028268a1669ac38f",
+                [
+                    "6ef0fc20a41e66fbab7d2af53d4a22e1e302a26039a17bc3831b04267fdb371d",
+                    "90947a64a3f0b578c35a66c8426e858fdc23eb89e7c0c82de9c7aaec66a47128",
+                ],
+            ),
+            data_feed(
+                1721923123,
+                "ETH/USD",
+                "0000000000000000000000000000000000000000000000aa03dc9c5b16eaa131",
+                [
+                    "902a6fa791e8592fa16204552aabfbc857b627bb4c9656c109223867ac7a32b2",
+                    "0a8b2f28a2860cc023c5e8a4b71e695f9f4c79d5acbdba882aece6ec59b8d820",
+                ],
+            ),
+            data_feed(
+                1721923134,
+                "BTC/USD",
+                "000000000000000000000000000000000000000000000db1bc1db5c37d9b53a2",
+                [
+                    "69ca37b692f2ba7d5058b91902ebd1513e327f6204f5f978209d40b9f640e09d",
+                    "90947a64a3f0b578c35a66c8426e858fdc23eb89e7c0c82de9c7aaec66a47128",
+                ],
+            ),
+        ],
+        signatures: vec![],
+    }
+}
@@ -2,15 +2,26 @@ use anchor_lang::prelude::*;
 use anchor_lang::solana_program::secp256k1_recover::secp256k1_recover;
 use sha3::{Digest, Keccak256};
 
-use crate::data::TransmitterSignature;
+use crate::data::TransmitterSignatureV2;
 use crate::error::CustomError;
 use crate::EthAddress;
 
-pub fn ecrecover(hash: &[u8], sig: &TransmitterSignature) -> anchor_lang::Result<EthAddress> {
+/// Half of the secp256k1 curve order, the signatures with a greater s are rejected as malleable
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+pub fn ecrecover(hash: &[u8], sig: &TransmitterSignatureV2) -> anchor_lang::Result<EthAddress> {
+    let recovery_id = match sig.v {
+        0 | 1 => sig.v,
+        27 | 28 => sig.v - 27,
+        _ => return Err(CustomError::InvalidSignatureRecoveryId.into()),
+    };
+    require!(sig.s <= SECP256K1_HALF_ORDER, CustomError::SignatureHighS);
     let signature = [&sig.r[..], &sig.s[..]].concat();
-    let v = sig.v % 27;
-    require_eq!(signature.len(), 64);
-    let pk = secp256k1_recover(hash, v, &signature).map_err(|_| CustomError::InvalidSignature)?;
+    let pk = secp256k1_recover(hash, recovery_id, &signature)
+        .map_err(|_| CustomError::InvalidSignature)?;
     Ok(derive_eth_address(&[&[0x04], &pk.0[..]].concat()))
 }
 
@@ -20,3 +31,94 @@ pub fn derive_eth_address(public_key: &[u8]) -> EthAddress {
     bytes.copy_from_slice(&hash[12..]);
     bytes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::TransmitterSignature,
+        signing::eth_signed_message_digest,
+        test_utils::{
+            bytes32, single_update_signature, SINGLE_UPDATE_MERKLE_ROOT, SINGLE_UPDATE_TRANSMITTER,
+        },
+    };
+
+    /// The order of the secp256k1 curve
+    const SECP256K1_ORDER: [u8; 32] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36,
+        0x41, 0x41,
+    ];
+
+    /// The digest and the signature of the first transmitter of the single price update test
+    fn signed_digest() -> ([u8; 32], TransmitterSignatureV2) {
+        let merkle_root = bytes32(SINGLE_UPDATE_MERKLE_ROOT);
+        (eth_signed_message_digest(&merkle_root), single_update_signature())
+    }
+
+    /// The other signature of the same digest, with s replaced by n - s and the parity flipped
+    fn malleated(signature: &TransmitterSignatureV2) -> TransmitterSignatureV2 {
+        let mut s = [0u8; 32];
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let diff = SECP256K1_ORDER[i] as i16 - signature.s[i] as i16 - borrow;
+            borrow = (diff < 0) as i16;
+            s[i] = diff.rem_euclid(256) as u8;
+        }
+        TransmitterSignatureV2 {
+            v: if signature.v == 27 { 28 } else { 27 },
+            r: signature.r,
+            s,
+        }
+    }
+
+    #[test]
+    fn recovers_transmitter() {
+        let (digest, signature) = signed_digest();
+        let transmitter = ecrecover(&digest, &signature).unwrap();
+        assert_eq!(hex::encode(transmitter), SINGLE_UPDATE_TRANSMITTER);
+
+        let signature = TransmitterSignatureV2 {
+            v: signature.v - 27,
+            ..signature
+        };
+        assert_eq!(ecrecover(&digest, &signature).unwrap(), transmitter);
+    }
+
+    #[test]
+    fn rejects_high_s() {
+        let (digest, signature) = signed_digest();
+        let signature = malleated(&signature);
+        assert!(signature.s > SECP256K1_HALF_ORDER);
+        assert_eq!(ecrecover(&digest, &signature).unwrap_err(), CustomError::SignatureHighS.into());
+    }
+
+    #[test]
+    fn rejects_invalid_recovery_id() {
+        let (digest, signature) = signed_digest();
+        for v in [2, 26, 29, 255] {
+            let signature = TransmitterSignatureV2 { v, ..signature };
+            assert_eq!(
+                ecrecover(&digest, &signature).unwrap_err(),
+                CustomError::InvalidSignatureRecoveryId.into()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_signature_length() {
+        let (_, signature) = signed_digest();
+        let lengths = [(31, 32), (33, 32), (32, 31), (32, 33), (0, 0), (64, 0)];
+        for (r_len, s_len) in lengths {
+            let signature = TransmitterSignature {
+                v: signature.v,
+                r: signature.r.iter().copied().cycle().take(r_len).collect(),
+                s: signature.s.iter().copied().cycle().take(s_len).collect(),
+            };
+            assert!(matches!(
+                TransmitterSignatureV2::try_from(&signature),
+                Err(CustomError::InvalidSignatureLength)
+            ));
+        }
+    }
+}
@@ -0,0 +1,99 @@
+import BN from "bn.js";
+import { ethers } from "ethers";
+
+const abiCoder = ethers.utils.defaultAbiCoder;
+
+function keccak256(data: ethers.utils.BytesLike): Buffer {
+    return Buffer.from(ethers.utils.arrayify(ethers.utils.keccak256(data)));
+}
+
+// The leaf of a data feed the same way as `udf_solana::data_feed_leaf` computes it
+export function dataFeedLeaf(timestamp: BN, dataKey: number[], data: number[]): Buffer {
+    const encoded = abiCoder.encode(
+        ["uint256", "bytes", "bytes32"],
+        [timestamp.toString(), data, dataKey]
+    );
+    return keccak256(keccak256(encoded));
+}
+
+// The leaf of a data feed along with its confidence and number of sources the same way as
+// `udf_solana::confidence_data_feed_leaf` computes it
+export function confidenceDataFeedLeaf(
+    timestamp: BN,
+    dataKey: number[],
+    data: number[],
+    confidence: number[],
+    sources: number
+): Buffer {
+    const encoded = abiCoder.encode(
+        ["uint256", "bytes", "bytes32", "bytes", "uint256"],
+        [timestamp.toString(), data, dataKey, confidence, sources]
+    );
+    return keccak256(keccak256(encoded));
+}
+
+function hashPair(a: Buffer, b: Buffer): Buffer {
+    return Buffer.compare(a, b) < 0
+        ? keccak256(Buffer.concat([a, b]))
+        : keccak256(Buffer.concat([b, a]));
+}
+
+export interface MultiProof {
+    leaves: Buffer[];
+    proof: Buffer[];
+    proofFlags: boolean[];
+}
+
+// The merkle tree of the signed data feeds, laid out as the OpenZeppelin `StandardMerkleTree`: a
+// complete binary tree in an array with the root first and the leaves last in reverse order
+export class MerkleTree {
+    readonly tree: Buffer[];
+
+    constructor(leaves: Buffer[]) {
+        this.tree = new Array(2 * leaves.length - 1);
+        leaves.forEach((leaf, i) => (this.tree[this.tree.length - 1 - i] = leaf));
+        for (let i = this.tree.length - 1 - leaves.length; i >= 0; i--) {
+            this.tree[i] = hashPair(this.tree[2 * i + 1], this.tree[2 * i + 2]);
+        }
+    }
+
+    get root(): Buffer {
+        return this.tree[0];
+    }
+
+    private treeIndex(leafIndex: number): number {
+        return this.tree.length - 1 - leafIndex;
+    }
+
+    getProof(leafIndex: number): Buffer[] {
+        const proof = [];
+        for (let j = this.treeIndex(leafIndex); j > 0; j = (j - 1) >> 1) {
+            proof.push(this.tree[j % 2 === 1 ? j + 1 : j - 1]);
+        }
+        return proof;
+    }
+
+    // The multiproof of the leaves, which are returned in the order they are to be passed in
+    getMultiProof(leafIndices: number[]): MultiProof {
+        const indices = leafIndices.map((i) => this.treeIndex(i)).sort((a, b) => b - a);
+        const stack = indices.slice();
+        const proof = [];
+        const proofFlags = [];
+        while (stack.length > 0 && stack[0] > 0) {
+            const j = stack.shift();
+            const sibling = j % 2 === 1 ? j + 1 : j - 1;
+            if (stack[0] === sibling) {
+                proofFlags.push(true);
+                stack.shift();
+            } else {
+                proofFlags.push(false);
+                proof.push(this.tree[sibling]);
+            }
+            stack.push((j - 1) >> 1);
+        }
+        if (indices.length === 0) {
+            proof.push(this.tree[0]);
+        }
+        return { leaves: indices.map((i) => this.tree[i]), proof, proofFlags };
+    }
+}
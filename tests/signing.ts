@@ -0,0 +1,67 @@
+import BN from "bn.js";
+import { ethers } from "ethers";
+import { web3 } from "@coral-xyz/anchor";
+
+const abiCoder = ethers.utils.defaultAbiCoder;
+
+// Mirrors the constants of `udf_solana::signing`
+const SIGNING_DOMAIN_VERSION = 1;
+const DOMAIN_TYPE =
+    "EIP712Domain(bytes32 protocolId,uint256 chainId,bytes32 programId,uint256 version)";
+const MERKLE_ROOT_TYPE = "MerkleRoot(bytes32 merkleRoot)";
+
+export interface TransmitterSignature {
+    v: number;
+    r: Buffer;
+    s: Buffer;
+}
+
+// The transmitters the photon mock is initialized with to sign the test updates, their keys are
+// derived from fixed seeds so the signatures are reproducible
+export const TEST_TRANSMITTERS = [0, 1, 2].map(
+    (i) => new ethers.Wallet(ethers.utils.id(`udf-solana test transmitter ${i}`))
+);
+
+export function transmitterAddress(wallet: ethers.Wallet): number[] {
+    return Array.from(ethers.utils.arrayify(wallet.address));
+}
+
+export function ethSignedMessageDigest(merkleRoot: Buffer): Buffer {
+    return Buffer.from(ethers.utils.arrayify(ethers.utils.hashMessage(merkleRoot)));
+}
+
+export function domainSeparator(protocolId: Buffer, chainId: BN, programId: web3.PublicKey): Buffer {
+    const encoded = abiCoder.encode(
+        ["bytes32", "bytes32", "uint256", "bytes32", "uint256"],
+        [
+            ethers.utils.id(DOMAIN_TYPE),
+            protocolId,
+            chainId.toString(),
+            programId.toBuffer(),
+            SIGNING_DOMAIN_VERSION,
+        ]
+    );
+    return Buffer.from(ethers.utils.arrayify(ethers.utils.keccak256(encoded)));
+}
+
+export function domainSeparatedDigest(domainSeparator: Buffer, merkleRoot: Buffer): Buffer {
+    const structHash = ethers.utils.keccak256(
+        abiCoder.encode(["bytes32", "bytes32"], [ethers.utils.id(MERKLE_ROOT_TYPE), merkleRoot])
+    );
+    const digest = ethers.utils.keccak256(
+        ethers.utils.concat(["0x1901", domainSeparator, structHash])
+    );
+    return Buffer.from(ethers.utils.arrayify(digest));
+}
+
+// Signs the digest by every wallet, the signatures are canonical with the low s and v of 27 or 28
+export function signDigest(wallets: ethers.Wallet[], digest: Buffer): TransmitterSignature[] {
+    return wallets.map((wallet) => {
+        const signature = wallet._signingKey().signDigest(digest);
+        return {
+            v: signature.v,
+            r: Buffer.from(ethers.utils.arrayify(signature.r)),
+            s: Buffer.from(ethers.utils.arrayify(signature.s)),
+        };
+    });
+}
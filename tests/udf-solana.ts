@@ -7,6 +7,16 @@ import { utf8 } from "@coral-xyz/anchor/dist/cjs/utils/bytes";
 import { Program, web3 } from "@coral-xyz/anchor";
 
 import { fetchPriceFeed } from "./util"
+import { confidenceDataFeedLeaf, dataFeedLeaf, MerkleTree, MultiProof } from "./merkle";
+import {
+    domainSeparatedDigest,
+    domainSeparator,
+    ethSignedMessageDigest,
+    signDigest,
+    TEST_TRANSMITTERS,
+    TransmitterSignature,
+    transmitterAddress,
+} from "./signing";
 import BN from "bn.js";
 import { base64 } from "ethers/lib/utils";
 import * as assert from "node:assert";
@@ -25,6 +35,12 @@ const UDF_ROOT = utf8.encode("UDF0");
 const PHOTON_ROOT = utf8.encode("r0");
 const CONSUMER_POOL_ROOT = utf8.encode("CONSUMER_PULL");
 
+const TRANSMITTERS = [
+    Array.from(Buffer.from("EfCF6f62254F76d9eF9bc06C843EAc97F0aA1723", "hex")),
+    Array.from(Buffer.from("E8a5D7DE6c51ae39326f93180F610F5cb8f0B4CC", "hex")),
+    Array.from(Buffer.from("BC506a4af4d452c2908CE6c590028EFD8EEC7962", "hex"))
+];
+
 const FinalizedSnapUrl = "https://pricefeed.entangle.fi";
 const FinalizedSourceID = "prices-feed1";
 
@@ -42,11 +58,15 @@ describe("udf-solana", () => {
     const consumer_pull_program = anchor.workspace.PriceConsumerPull as Program<PriceConsumerPull>
 
     const govExecutor = anchor.web3.Keypair.fromSecretKey(Uint8Array.from(require("../keys/gov-executor.json")));
+    // The read fee accounts of the price reads, left out while the read fee is disabled
+    const freeRead = { payer: null, subscription: null, treasury: null, systemProgram: null };
+
     const owner = anchor.web3.Keypair.fromSecretKey(Uint8Array.from(require("../keys/owner.json")));
     const publisher = anchor.web3.Keypair.fromSecretKey(Uint8Array.from(require("../keys/publisher.json")));
 
     let transmitters = [];
     let udfConfig = null;
+    let udfTreasury = null;
     let udfProtocolInfo = null;
     let photonGovProtocolInfo = null;
     let photonConfig = null;
@@ -67,6 +87,11 @@ describe("udf-solana", () => {
             udf_program.programId
         )[0];
 
+        udfTreasury = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("TREASURY")],
+            udf_program.programId
+        )[0];
+
         udfProtocolInfo = web3.PublicKey.findProgramAddressSync(
             [PHOTON_ROOT, utf8.encode("PROTOCOL"),
                 UDF_PROTOCOL_ID], ccm_program.programId
@@ -86,14 +111,7 @@ describe("udf-solana", () => {
     });
 
     it("Initialize photon mock", async () => {
-
-        let transmitters = [
-            Array.from(Buffer.from("EfCF6f62254F76d9eF9bc06C843EAc97F0aA1723", "hex")),
-            Array.from(Buffer.from("E8a5D7DE6c51ae39326f93180F610F5cb8f0B4CC", "hex")),
-            Array.from(Buffer.from("BC506a4af4d452c2908CE6c590028EFD8EEC7962", "hex"))
-        ];
-
-        const tx = await ccm_program.methods.initialize(transmitters)
+        const tx = await ccm_program.methods.initialize(TRANSMITTERS)
             .accounts({
                 admin: owner.publicKey,
                 protocolInfo: udfProtocolInfo,
@@ -460,15 +478,704 @@ describe("udf-solana", () => {
             [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, dataKey],
             udf_program_id
         )[0];
-        const getLastPriceTx = await consumer_program.methods.consumePrice(dataKey)
+        const treasury = web3.PublicKey.findProgramAddressSync([UDF_ROOT, utf8.encode("TREASURY")], udf_program_id)[0];
+        const getLastPriceTx = await consumer_program.methods.consumePrice({ key: Array.from(dataKey) })
             .accounts({
-                signer: owner.publicKey,
+                payer: owner.publicKey,
                 priceOracle: udf_program_id,
                 latestUpdate: latestUpdatePda,
+                config: udfConfig,
+                treasury: treasury,
+                systemProgram: web3.SystemProgram.programId,
             })
             .signers([owner])
             .rpc();
         console.log("Consume tx signature", getLastPriceTx);
     })
-});
 
+    it("Verify price without storing it", async () => {
+        let utf8Encode = new TextEncoder();
+
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8Encode.encode("NGL/USD"));
+
+        const data = Array.from(Buffer.from("00000000000000000000000000000000000000000000000001cd7dccedfae367", "hex"));
+        const timestamp = new anchor.BN(1723502724);
+        let lastPriceData: LastPriceData = {
+            dataFeed: {
+                timestamp: timestamp,
+                dataKey: Array.from(dataKey),
+                data: data,
+                merkleProof: [
+                    Array.from(Buffer.from("387d19e56e66e06b0b7209189a1a66dfbb2b87a4fe56f9cb9f6e4b813a01e821", "hex")),
+                    Array.from(Buffer.from("f839cf170d834cb6312691d75ce378149a2b9a0b9d7a5c7c07c3ca6a66286b4b", "hex"))
+                ],
+            },
+            signatures: [{
+                v: 28,
+                r: Buffer.from("5116c928d3a13a47d2f1c055e57564280f2f455a433d9360292bd8a57f428155", "hex"),
+                s: Buffer.from("13df35b89f982306fc42f4e2f074880bc7b5e9d2df6febc6bd7e38e3f8d1b831", "hex"),
+            }, {
+                v: 28,
+                r: Buffer.from("0d09f78606915f60531f3a45b1bb62b75755254eedb58291d4a7805248f2e723", "hex"),
+                s: Buffer.from("0cccc0bed37328faa9e6e0a35a63164ef248c785fb58595fe629cac54b1ab08c", "hex"),
+            }, {
+                v: 27,
+                r: Buffer.from("025aa961b80bdec312a87d48c9e084bb5427316c7b0cbd7c79f8fc9f75aced82", "hex"),
+                s: Buffer.from("029a25af80fff863a390bcbc967e2e8b17d0aa6da90098577192a3ed4e767d1d", "hex"),
+            }],
+            merkleRoot: Array.from(Buffer.from("9572fbbba8b66755f38c81e65ae0d13b087f31d86d5e746ca17e27bf2da38d06", "hex"))
+        }
+
+        const verifyPriceTx = await udf_program.methods.verifyPrice(lastPriceData)
+            .accounts({
+                config: udfConfig,
+                protocolInfo: udfProtocolInfo,
+            })
+            .preInstructions([web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })])
+            .transaction();
+        const result = await udf_program.provider.simulate(verifyPriceTx);
+        // Trailing zeros of the return data are truncated by the runtime
+        const returnData = Buffer.from(result.returnData.data[0], "base64");
+        assert.deepEqual(Array.from(returnData.subarray(0, 32)), data);
+        assert.ok(new BN(returnData.subarray(32), "le").eq(timestamp));
+    });
+
+    it("Fund treasury", async () => {
+        const treasury = web3.PublicKey.findProgramAddressSync([UDF_ROOT, utf8.encode("TREASURY")], udf_program.programId)[0];
+        const amount = new anchor.BN(web3.LAMPORTS_PER_SOL);
+        await udf_program.methods.fundTreasury(amount)
+            .accounts({ funder: owner.publicKey, treasury, systemProgram: web3.SystemProgram.programId })
+            .signers([owner])
+            .rpc();
+
+        const balance = await udf_program.provider.connection.getBalance(treasury);
+        assert.ok(balance >= web3.LAMPORTS_PER_SOL);
+        const treasuryAccount = await udf_program.account.treasury.fetch(treasury);
+        assert.ok(treasuryAccount.totalReimbursed.eqn(0));
+    });
+
+    async function setFeedMetadata(dataKey: Uint8Array, heartbeat: number, paused: boolean) {
+        const latestUpdatePda = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, dataKey],
+            udf_program.programId
+        )[0];
+        await udf_program.methods.setFeedMetadata(Array.from(dataKey), heartbeat, paused)
+            .accounts({ admin: owner.publicKey, config: udfConfig, latestUpdate: latestUpdatePda })
+            .signers([owner])
+            .rpc();
+    }
+
+    async function simulateLastPrice(dataKey: Uint8Array, versioned: boolean): Promise<Buffer> {
+        const latestUpdatePda = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, dataKey],
+            udf_program.programId
+        )[0];
+        const read = versioned ? udf_program.methods.lastPriceV2 : udf_program.methods.lastPrice;
+        const lastPriceTx = await read({ key: Array.from(dataKey) })
+            .accounts({ lastPrice: latestUpdatePda, config: udfConfig, ...freeRead })
+            .transaction();
+        const result = await udf_program.provider.simulate(lastPriceTx);
+        return Buffer.from(result.returnData.data[0], "base64");
+    }
+
+    async function readFeedStatus(dataKey: Uint8Array): Promise<number> {
+        const returnData = await simulateLastPrice(dataKey, true);
+        // The status of (data, timestamp, status) is the last byte, the trailing zero of the
+        // fresh status is truncated by the runtime
+        return returnData.length > 40 ? returnData[40] : 0;
+    }
+
+    it("Report paused feed status", async () => {
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("NGL/USD"));
+
+        await setFeedMetadata(dataKey, 60, true);
+        try {
+            // 2 stands for paused
+            assert.equal(await readFeedStatus(dataKey), 2);
+            // The unversioned read keeps returning (data, timestamp) for the existing consumers
+            assert.isAtMost((await simulateLastPrice(dataKey, false)).length, 40);
+        } finally {
+            await setFeedMetadata(dataKey, 0, false);
+        }
+    });
+
+    it("Report stale feed status", async () => {
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("NGL/USD"));
+
+        // The feed is last updated long before the heartbeat of a minute, 1 stands for stale
+        await setFeedMetadata(dataKey, 60, false);
+        try {
+            assert.equal(await readFeedStatus(dataKey), 1);
+        } finally {
+            await setFeedMetadata(dataKey, 0, false);
+        }
+    });
+
+    it("Report fresh feed status", async () => {
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("NGL/USD"));
+
+        // Without the heartbeat the feed is never stale, 0 stands for fresh
+        assert.equal(await readFeedStatus(dataKey), 0);
+
+        // The feed is updated within the heartbeat of the max interval
+        await setFeedMetadata(dataKey, 0xffffffff, false);
+        try {
+            assert.equal(await readFeedStatus(dataKey), 0);
+        } finally {
+            await setFeedMetadata(dataKey, 0, false);
+        }
+    });
+
+    it("Reject reads of not migrated feed", async () => {
+        // The account of the feed is loaded from the fixture in the layout before the confidence
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("LEGACY/USD"));
+        const latestUpdatePda = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, dataKey],
+            udf_program.programId
+        )[0];
+        const legacyAccount = await udf_program.provider.connection.getAccountInfo(latestUpdatePda);
+        assert.equal(legacyAccount.data.length, 80);
+
+        for (const read of [
+            () => udf_program.methods.lastPrice({ key: Array.from(dataKey) })
+                .accounts({ lastPrice: latestUpdatePda, config: udfConfig, ...freeRead })
+                .rpc(),
+            () => udf_program.methods.lastPriceWithConfidence({ key: Array.from(dataKey) })
+                .accounts({ lastPrice: latestUpdatePda, config: udfConfig, ...freeRead })
+                .rpc(),
+            () => setFeedMetadata(dataKey, 60, false),
+        ]) {
+            try {
+                await read();
+                assert.fail("Read of the not migrated feed is expected to be rejected");
+            } catch (err) {
+                assert.ok(err instanceof anchor.AnchorError);
+                assert.equal(err.error.errorCode.code, "AccountNotMigrated");
+            }
+        }
+
+        await udf_program.methods.migrateLatestUpdate(Array.from(dataKey))
+            .accounts({
+                payer: owner.publicKey,
+                config: udfConfig,
+                latestUpdate: latestUpdatePda,
+                systemProgram: web3.SystemProgram.programId
+            })
+            .signers([owner])
+            .rpc();
+        const latestUpdate = await udf_program.account.latestUpdate.fetch(latestUpdatePda);
+        assert.ok(latestUpdate.data.slice(0, 31).every((byte) => byte === 0));
+        assert.equal(latestUpdate.data[31], 42);
+        assert.ok(latestUpdate.dataTimestamp.eqn(1700000000));
+        assert.equal(await readFeedStatus(dataKey), 0);
+    });
+
+    it("Reject signatures of the other signature scheme", async () => {
+        let utf8Encode = new TextEncoder();
+
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8Encode.encode("NGL/USD"));
+        let latestUpdatePda = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, dataKey],
+            udf_program.programId
+        )[0];
+
+        let dataFeedMessage: MultipleUpdateData = {
+            dataFeeds: [{
+                timestamp: new anchor.BN(1721838418),
+                dataKey: Array.from(dataKey),
+                data: Array.from(Buffer.from("00000000000000000000000000000000000000000000000002b6498fc05aba1b", "hex")),
+                merkleProof: [
+                    Array.from(Buffer.from("d9c4cda803450076940306457745ed3ab6f4875f4e5086931f21a9c2da8d64f9", "hex")),
+                    Array.from(Buffer.from("2754d47eba0af64135b87baa5a6e6324aa020f58196264145e613aa2f78bf926", "hex"))
+                ],
+            }],
+            signatures: [{
+                v: 28,
+                r: Buffer.from("d92028d242d7b7b054bfa9b5a9f336d4352b5b1da3cafd71288c14b3f9ae722c", "hex"),
+                s: Buffer.from("6053b190e6f39c7b08cc37b68ac1d8ecd9ab735cdade4cc898724760777da98a", "hex"),
+            }, {
+                v: 28,
+                r: Buffer.from("ba6b24be0611a4796873c47723f56928c7fc71edfab9862e2a7511a72b5426a0", "hex"),
+                s: Buffer.from("26c22606b533b645e9cb74b25d8e081e985727e739b1c643e1959c8000c4c9a0", "hex"),
+            }, {
+                v: 28,
+                r: Buffer.from("b40e33efefeff5901b5ef7f94762cfb805ba3cfdeaae3c09517d7c3160d187ed", "hex"),
+                s: Buffer.from("4ad1df603b191603234faca397ac2148805bd86ec7c8d0f093c516a455a7bcf3", "hex"),
+            }],
+            merkleRoot: Array.from(Buffer.from("07963960682c8bf05845f099078d6839bf6f6d6d159f36adc32dcff465c71b56", "hex"))
+        }
+
+        await udf_program.methods.setSignatureScheme({ domainSeparated: {} }, new anchor.BN("11100000000000000501"))
+            .accounts({ admin: owner.publicKey, config: udfConfig })
+            .signers([owner]).rpc();
+
+        try {
+            await udf_program.methods.updateMultipleAssets(dataFeedMessage)
+                .accounts({
+                    publisher: publisher.publicKey,
+                    config: udfConfig,
+                    protocolInfo: udfProtocolInfo,
+                    systemProgram: web3.SystemProgram.programId
+                })
+                .remainingAccounts([{ pubkey: latestUpdatePda, isSigner: false, isWritable: true }])
+                .preInstructions([web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })])
+                .signers([publisher]).rpc();
+            assert.fail("Update signed for the eth signed message scheme is expected to be rejected");
+        } catch (err) {
+            assert.ok(err instanceof anchor.AnchorError);
+            assert.equal(err.error.errorCode.code, "ConsensusNotReached");
+        } finally {
+            await udf_program.methods.setSignatureScheme({ ethSignedMessage: {} }, new anchor.BN(0))
+                .accounts({ admin: owner.publicKey, config: udfConfig })
+                .signers([owner]).rpc();
+        }
+    })
+
+    async function setReadFees(readFee: number, subscriptionDailyFee: number) {
+        await udf_program.methods.setReadFees(new BN(readFee), new BN(subscriptionDailyFee))
+            .accounts({ admin: owner.publicKey, config: udfConfig })
+            .signers([owner])
+            .rpc();
+    }
+
+    async function readLastPrice(dataKey: Uint8Array, readFeeAccounts, signers: web3.Keypair[]) {
+        const latestUpdatePda = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, dataKey],
+            udf_program.programId
+        )[0];
+        await udf_program.methods.lastPrice({ key: Array.from(dataKey) })
+            .accounts({ lastPrice: latestUpdatePda, config: udfConfig, ...readFeeAccounts })
+            .signers(signers)
+            .rpc();
+    }
+
+    async function subscribe(days: number): Promise<[web3.Keypair, web3.PublicKey]> {
+        const subscriber = web3.Keypair.generate();
+        await udf_program.provider.sendAndConfirm(
+            new web3.Transaction().add(web3.SystemProgram.transfer({
+                fromPubkey: owner.publicKey,
+                toPubkey: subscriber.publicKey,
+                lamports: web3.LAMPORTS_PER_SOL / 10,
+            })),
+            [owner]
+        );
+        const subscription = web3.PublicKey.findProgramAddressSync(
+            [UDF_ROOT, utf8.encode("SUBSCRIPTION"), subscriber.publicKey.toBuffer()],
+            udf_program.programId
+        )[0];
+        await udf_program.methods.subscribe(days)
+            .accounts({
+                subscriber: subscriber.publicKey,
+                config: udfConfig,
+                subscription,
+                treasury: udfTreasury,
+                systemProgram: web3.SystemProgram.programId
+            })
+            .signers([subscriber])
+            .rpc();
+        return [subscriber, subscription];
+    }
+
+    async function expectReadFeeNotPaid(read: Promise<void>) {
+        try {
+            await read;
+            assert.fail("Read is expected to be rejected without the read fee paid");
+        } catch (err) {
+            assert.ok(err instanceof anchor.AnchorError);
+            assert.equal(err.error.errorCode.code, "ReadFeeNotPaid");
+        }
+    }
+
+    it("Charge read fee", async () => {
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("NGL/USD"));
+
+        await setReadFees(1000, 0);
+        try {
+            await expectReadFeeNotPaid(readLastPrice(dataKey, freeRead, []));
+            // The payer alone doesn't pay without the treasury
+            await expectReadFeeNotPaid(readLastPrice(dataKey, { ...freeRead, payer: owner.publicKey }, [owner]));
+
+            const balance = await udf_program.provider.connection.getBalance(udfTreasury);
+            await readLastPrice(dataKey, {
+                payer: owner.publicKey,
+                subscription: null,
+                treasury: udfTreasury,
+                systemProgram: web3.SystemProgram.programId,
+            }, [owner]);
+            assert.equal(await udf_program.provider.connection.getBalance(udfTreasury) - balance, 1000);
+        } finally {
+            await setReadFees(0, 0);
+        }
+    });
+
+    it("Skip read fee for active subscription", async () => {
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("NGL/USD"));
+
+        await setReadFees(1000, 500);
+        try {
+            const [subscriber, subscription] = await subscribe(1);
+            const balance = await udf_program.provider.connection.getBalance(udfTreasury);
+            await readLastPrice(dataKey, { ...freeRead, payer: subscriber.publicKey, subscription }, [subscriber]);
+            assert.equal(await udf_program.provider.connection.getBalance(udfTreasury), balance);
+
+            // The subscription of another payer isn't accepted
+            await expectReadFeeNotPaid(readLastPrice(dataKey, { ...freeRead, payer: owner.publicKey, subscription }, [owner]));
+        } finally {
+            await setReadFees(0, 0);
+        }
+    });
+
+    it("Reject expired subscription", async () => {
+        const dataKey = new Uint8Array(32);
+        dataKey.set(utf8.encode("NGL/USD"));
+
+        await setReadFees(1000, 500);
+        try {
+            // The subscription of zero days expires at the time it's paid for
+            const [subscriber, subscription] = await subscribe(0);
+            await expectReadFeeNotPaid(readLastPrice(dataKey, { ...freeRead, payer: subscriber.publicKey, subscription }, [subscriber]));
+        } finally {
+            await setReadFees(0, 0);
+        }
+    });
+
+    it("Keep treasury rent exempt on withdrawal", async () => {
+        const treasuryAccount = await udf_program.provider.connection.getAccountInfo(udfTreasury);
+        const rent = await udf_program.provider.connection.getMinimumBalanceForRentExemption(treasuryAccount.data.length);
+        const available = treasuryAccount.lamports - rent;
+        const recipient = web3.Keypair.generate().publicKey;
+
+        try {
+            await udf_program.methods.withdrawTreasury(new BN(available + 1))
+                .accounts({ admin: owner.publicKey, config: udfConfig, treasury: udfTreasury, recipient })
+                .signers([owner])
+                .rpc();
+            assert.fail("Withdrawal below the rent exempt balance is expected to be rejected");
+        } catch (err) {
+            assert.ok(err instanceof anchor.AnchorError);
+            assert.equal(err.error.errorCode.code, "InsufficientTreasuryFunds");
+        }
+
+        // The recipient is funded to be rent exempt itself
+        await udf_program.methods.withdrawTreasury(new BN(web3.LAMPORTS_PER_SOL / 100))
+            .accounts({ admin: owner.publicKey, config: udfConfig, treasury: udfTreasury, recipient })
+            .signers([owner])
+            .rpc();
+        assert.equal(await udf_program.provider.connection.getBalance(recipient), web3.LAMPORTS_PER_SOL / 100);
+    });
+
+    // The updates of the nested tests are built and signed by the test transmitters instead of
+    // the recorded ones, mocha runs them after the tests above
+    describe("signed by the test transmitters", () => {
+        const timestamp = new anchor.BN(Math.floor(Date.now() / 1000));
+
+        interface TestFeed {
+            timestamp: anchor.BN;
+            dataKey: number[];
+            data: number[];
+        }
+
+        function testFeed(asset: string, price: number, timestamp: anchor.BN): TestFeed {
+            const dataKey = new Uint8Array(32);
+            dataKey.set(utf8.encode(asset));
+            return {
+                timestamp,
+                dataKey: Array.from(dataKey),
+                data: new BN(price).toArray("be", 32),
+            };
+        }
+
+        function latestUpdatePda(dataKey: number[]): web3.PublicKey {
+            return web3.PublicKey.findProgramAddressSync(
+                [UDF_ROOT, utf8.encode("LAST_UPDATE"), UDF_PROTOCOL_ID, Buffer.from(dataKey)],
+                udf_program.programId
+            )[0];
+        }
+
+        function buildUpdate(
+            feeds: TestFeed[],
+            digest: (merkleRoot: Buffer) => Buffer = ethSignedMessageDigest
+        ): MultipleUpdateData {
+            const tree = new MerkleTree(
+                feeds.map((feed) => dataFeedLeaf(feed.timestamp, feed.dataKey, feed.data))
+            );
+            return {
+                dataFeeds: feeds.map((feed, i) => ({
+                    ...feed,
+                    merkleProof: tree.getProof(i).map((node) => Array.from(node)),
+                })),
+                signatures: signDigest(TEST_TRANSMITTERS, digest(tree.root)),
+                merkleRoot: Array.from(tree.root),
+            };
+        }
+
+        async function sendUpdate(update: MultipleUpdateData, remainingAccounts: web3.AccountMeta[] = []) {
+            return await udf_program.methods.updateMultipleAssets(update)
+                .accounts({
+                    publisher: publisher.publicKey,
+                    config: udfConfig,
+                    protocolInfo: udfProtocolInfo,
+                    systemProgram: web3.SystemProgram.programId
+                })
+                .remainingAccounts(update.dataFeeds.map((feed) => (
+                    { pubkey: latestUpdatePda(feed.dataKey), isSigner: false, isWritable: true }
+                )).concat(remainingAccounts))
+                .preInstructions([web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })])
+                .signers([publisher]).rpc();
+        }
+
+        async function initializeTransmitters(transmitters: number[][]) {
+            await ccm_program.methods.initialize(transmitters)
+                .accounts({
+                    admin: owner.publicKey,
+                    protocolInfo: udfProtocolInfo,
+                    systemProgram: web3.SystemProgram.programId
+                })
+                .signers([owner]).rpc();
+        }
+
+        before(async () => {
+            await initializeTransmitters(TEST_TRANSMITTERS.map(transmitterAddress));
+        });
+
+        after(async () => {
+            await initializeTransmitters(TRANSMITTERS);
+        });
+
+        it("Accept update signed for the domain separated scheme", async () => {
+            const chainId = new anchor.BN("11100000000000000501");
+            const feed = testFeed("SOL/USD", 150, timestamp);
+            const digest = (merkleRoot: Buffer) => domainSeparatedDigest(
+                domainSeparator(UDF_PROTOCOL_ID, chainId, udf_program.programId),
+                merkleRoot
+            );
+
+            await udf_program.methods.setSignatureScheme({ domainSeparated: {} }, chainId)
+                .accounts({ admin: owner.publicKey, config: udfConfig })
+                .signers([owner]).rpc();
+            try {
+                try {
+                    await sendUpdate(buildUpdate([feed]));
+                    assert.fail("Update signed for the eth signed message scheme is expected to be rejected");
+                } catch (err) {
+                    assert.ok(err instanceof anchor.AnchorError);
+                    assert.equal(err.error.errorCode.code, "ConsensusNotReached");
+                }
+
+                await sendUpdate(buildUpdate([feed], digest));
+                const latestUpdate = await udf_program.account.latestUpdate.fetch(latestUpdatePda(feed.dataKey));
+                assert.deepEqual(latestUpdate.data, feed.data);
+                assert.ok(latestUpdate.dataTimestamp.eq(feed.timestamp));
+            } finally {
+                await udf_program.methods.setSignatureScheme({ ethSignedMessage: {} }, new anchor.BN(0))
+                    .accounts({ admin: owner.publicKey, config: udfConfig })
+                    .signers([owner]).rpc();
+            }
+        });
+
+        it("Reject malformed signatures", async () => {
+            const secp256k1Order = new BN("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141", 16);
+            const feed = testFeed("SOL/USD", 151, timestamp.addn(1));
+
+            const malformations: [string, (signature: TransmitterSignature) => TransmitterSignature][] = [
+                // The other valid signature of the same digest, with s replaced by n - s
+                ["SignatureHighS", (signature) => ({
+                    v: signature.v === 27 ? 28 : 27,
+                    r: signature.r,
+                    s: secp256k1Order.sub(new BN(signature.s)).toArrayLike(Buffer, "be", 32),
+                })],
+                ["InvalidSignatureRecoveryId", (signature) => ({ ...signature, v: 29 })],
+                ["InvalidSignatureLength", (signature) => ({ ...signature, r: signature.r.subarray(1) })],
+                ["InvalidSignatureLength", (signature) => ({ ...signature, s: Buffer.concat([signature.s, Buffer.alloc(1)]) })],
+            ];
+            for (const [code, malform] of malformations) {
+                const update = buildUpdate([feed]);
+                update.signatures = [malform(update.signatures[0]), ...update.signatures.slice(1)];
+                try {
+                    await sendUpdate(update);
+                    assert.fail(`Update with the malformed signature is expected to be rejected with ${code}`);
+                } catch (err) {
+                    assert.ok(err instanceof anchor.AnchorError);
+                    assert.equal(err.error.errorCode.code, code);
+                }
+            }
+        });
+
+        function fixedSizeSignatures(signatures: TransmitterSignature[]) {
+            return signatures.map(({ v, r, s }) => ({ v, r: Array.from(r), s: Array.from(s) }));
+        }
+
+        async function sendMultiProofUpdate(feeds: TestFeed[], tree: MerkleTree, multiProof: MultiProof) {
+            return await udf_program.methods.updateMultipleAssetsMultiproof({
+                merkleRoot: Array.from(tree.root),
+                dataFeeds: feeds,
+                proof: multiProof.proof.map((node) => Array.from(node)),
+                proofFlags: multiProof.proofFlags,
+                signatures: fixedSizeSignatures(signDigest(TEST_TRANSMITTERS, ethSignedMessageDigest(tree.root))),
+            })
+                .accounts({
+                    publisher: publisher.publicKey,
+                    config: udfConfig,
+                    protocolInfo: udfProtocolInfo,
+                    systemProgram: web3.SystemProgram.programId
+                })
+                .remainingAccounts(feeds.map((feed) => (
+                    { pubkey: latestUpdatePda(feed.dataKey), isSigner: false, isWritable: true }
+                )))
+                .preInstructions([web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })])
+                .signers([publisher]).rpc();
+        }
+
+        it("Accept multiproof of several data feeds", async () => {
+            // The leaves of the multiproof of three out of four data feeds hash in pairs, while the
+            // proof of two of them is made of the other leaves only
+            for (const [offset, leafIndices] of [[0, [0, 1, 3]], [1, [0, 2]]] as [number, number[]][]) {
+                const feeds = ["AVAX/USD", "DOT/USD", "LINK/USD", "ATOM/USD"].map(
+                    (asset, i) => testFeed(asset, 10 + i, timestamp.addn(offset))
+                );
+                const tree = new MerkleTree(feeds.map((feed) => dataFeedLeaf(feed.timestamp, feed.dataKey, feed.data)));
+                const multiProof = tree.getMultiProof(leafIndices);
+                assert.equal(multiProof.leaves.length + multiProof.proof.length, multiProof.proofFlags.length + 1);
+
+                const provenFeeds = leafIndices.slice().sort((a, b) => a - b).map((i) => feeds[i]);
+                await sendMultiProofUpdate(provenFeeds, tree, multiProof);
+                for (const feed of provenFeeds) {
+                    const latestUpdate = await udf_program.account.latestUpdate.fetch(latestUpdatePda(feed.dataKey));
+                    assert.deepEqual(latestUpdate.data, feed.data);
+                    assert.ok(latestUpdate.dataTimestamp.eq(feed.timestamp));
+                }
+            }
+        });
+
+        it("Reject multiproof with flipped flag", async () => {
+            const feeds = ["AVAX/USD", "DOT/USD", "LINK/USD", "ATOM/USD"].map(
+                (asset, i) => testFeed(asset, 20 + i, timestamp.addn(2))
+            );
+            const tree = new MerkleTree(feeds.map((feed) => dataFeedLeaf(feed.timestamp, feed.dataKey, feed.data)));
+            const multiProof = tree.getMultiProof([0, 1, 3]);
+            multiProof.proofFlags[0] = !multiProof.proofFlags[0];
+            try {
+                await sendMultiProofUpdate([feeds[0], feeds[1], feeds[3]], tree, multiProof);
+                assert.fail("Multiproof with the flipped flag is expected to be rejected");
+            } catch (err) {
+                assert.ok(err instanceof anchor.AnchorError);
+                assert.equal(err.error.errorCode.code, "MerkleProofNotVerified");
+            }
+        });
+
+        async function sendConfidenceUpdate(feeds: TestFeed[], confidence: number, sources: number) {
+            const confidenceFeeds = feeds.map((feed) => ({
+                ...feed,
+                confidence: new BN(confidence).toArray("be", 32),
+                sources,
+            }));
+            const tree = new MerkleTree(confidenceFeeds.map((feed) => confidenceDataFeedLeaf(
+                feed.timestamp, feed.dataKey, feed.data, feed.confidence, feed.sources
+            )));
+            return await udf_program.methods.updateMultipleAssetsWithConfidence({
+                merkleRoot: Array.from(tree.root),
+                dataFeeds: confidenceFeeds.map((feed, i) => ({
+                    ...feed,
+                    merkleProof: tree.getProof(i).map((node) => Array.from(node)),
+                })),
+                signatures: fixedSizeSignatures(signDigest(TEST_TRANSMITTERS, ethSignedMessageDigest(tree.root))),
+            })
+                .accounts({
+                    publisher: publisher.publicKey,
+                    config: udfConfig,
+                    protocolInfo: udfProtocolInfo,
+                    systemProgram: web3.SystemProgram.programId
+                })
+                .remainingAccounts(feeds.map((feed) => (
+                    { pubkey: latestUpdatePda(feed.dataKey), isSigner: false, isWritable: true }
+                )))
+                .preInstructions([web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })])
+                .signers([publisher]).rpc();
+        }
+
+        async function readLastPriceWithConfidence(dataKey: number[]) {
+            const lastPriceTx = await udf_program.methods.lastPriceWithConfidenceV2({ key: dataKey })
+                .accounts({ lastPrice: latestUpdatePda(dataKey), config: udfConfig, ...freeRead })
+                .transaction();
+            const result = await udf_program.provider.simulate(lastPriceTx);
+            // (data, timestamp, confidence, sources, status) with the trailing zeros truncated by
+            // the runtime
+            const truncated = Buffer.from(result.returnData.data[0], "base64");
+            const returnData = Buffer.concat([truncated, Buffer.alloc(75 - truncated.length)]);
+            return {
+                data: Array.from(returnData.subarray(0, 32)),
+                timestamp: new BN(returnData.subarray(32, 40), "le"),
+                confidence: new BN(returnData.subarray(40, 72), "be"),
+                sources: returnData.readUInt16LE(72),
+            };
+        }
+
+        it("Store confidence of new and existing data feeds", async () => {
+            // The existing data feed is created by the update without confidence beforehand
+            const existingFeed = testFeed("XRP/USD", 1, timestamp);
+            await sendUpdate(buildUpdate([existingFeed]));
+
+            const feeds = [testFeed("XRP/USD", 2, timestamp.addn(1)), testFeed("ADA/USD", 3, timestamp.addn(1))];
+            await sendConfidenceUpdate(feeds, 7, 5);
+            for (const feed of feeds) {
+                const lastPrice = await readLastPriceWithConfidence(feed.dataKey);
+                assert.deepEqual(lastPrice.data, feed.data);
+                assert.ok(lastPrice.timestamp.eq(feed.timestamp));
+                assert.ok(lastPrice.confidence.eqn(7));
+                assert.equal(lastPrice.sources, 5);
+            }
+        });
+
+        it("Reimburse publisher from treasury up to the cap", async () => {
+            const treasury = web3.PublicKey.findProgramAddressSync([UDF_ROOT, utf8.encode("TREASURY")], udf_program.programId)[0];
+            const treasuryMeta = { pubkey: treasury, isSigner: false, isWritable: true };
+            const updateReward = 1000;
+            const latestUpdateRent = await udf_program.provider.connection.getMinimumBalanceForRentExemption(120);
+
+            async function reimbursedBy(update: MultipleUpdateData): Promise<number> {
+                const balance = await udf_program.provider.connection.getBalance(treasury);
+                const { totalReimbursed } = await udf_program.account.treasury.fetch(treasury);
+                await sendUpdate(update, [treasuryMeta]);
+                const reimbursed = balance - await udf_program.provider.connection.getBalance(treasury);
+                const treasuryAccount = await udf_program.account.treasury.fetch(treasury);
+                assert.ok(treasuryAccount.totalReimbursed.sub(totalReimbursed).eqn(reimbursed));
+                return reimbursed;
+            }
+
+            async function setPublisherRewards(updateReward: number, reimbursementCap: number,
+                rewardedPublisher: web3.PublicKey = publisher.publicKey) {
+                await udf_program.methods.setPublisherRewards(rewardedPublisher, new BN(updateReward), new BN(reimbursementCap))
+                    .accounts({ admin: owner.publicKey, config: udfConfig })
+                    .signers([owner]).rpc();
+            }
+
+            try {
+                // Both data feeds are created, the rent of their accounts is reimbursed along with
+                // the reward for each
+                await setPublisherRewards(updateReward, web3.LAMPORTS_PER_SOL);
+                const feeds = [testFeed("TRX/USD", 1, timestamp), testFeed("TON/USD", 2, timestamp)];
+                assert.equal(await reimbursedBy(buildUpdate(feeds)), 2 * latestUpdateRent + 2 * updateReward);
+
+                // The rewards of both updated data feeds are clamped by the cap
+                await setPublisherRewards(updateReward, 1500);
+                const updatedFeeds = [testFeed("TRX/USD", 3, timestamp.addn(1)), testFeed("TON/USD", 4, timestamp.addn(1))];
+                assert.equal(await reimbursedBy(buildUpdate(updatedFeeds)), 1500);
+
+                // Only the registered publisher is rewarded
+                await setPublisherRewards(updateReward, web3.LAMPORTS_PER_SOL, web3.Keypair.generate().publicKey);
+                const otherFeeds = [testFeed("TRX/USD", 5, timestamp.addn(2))];
+                assert.equal(await reimbursedBy(buildUpdate(otherFeeds)), 0);
+            } finally {
+                await setPublisherRewards(0, 0, web3.PublicKey.default);
+            }
+        });
+    });
+});
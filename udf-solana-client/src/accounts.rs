@@ -0,0 +1,171 @@
+use anchor_lang::{error::ErrorCode, AccountDeserialize, Discriminator};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use udf_solana::data::{Config, LatestUpdate, ProtocolInfo, Subscription, Treasury};
+
+use crate::{pda, ClientError};
+
+/// The max number of accounts a single getMultipleAccounts request returns
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+pub async fn fetch_account<T: AccountDeserialize>(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+) -> Result<T, ClientError> {
+    let account = fetch_raw_account(rpc_client, address).await?;
+    T::try_deserialize(&mut account.data.as_slice())
+        .map_err(|err| ClientError::AccountDeserialize(*address, err))
+}
+
+async fn fetch_raw_account(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+) -> Result<Account, ClientError> {
+    rpc_client
+        .get_account_with_commitment(address, rpc_client.commitment())
+        .await?
+        .value
+        .ok_or(ClientError::AccountNotFound(*address))
+}
+
+/// Decodes the zero copy latest update account. Accounts created before the current layout are
+/// shorter, the missing fields are read as zeros
+pub fn decode_latest_update(address: &Pubkey, data: &[u8]) -> Result<LatestUpdate, ClientError> {
+    if data.len() < 8 || data[..8] != LatestUpdate::DISCRIMINATOR || data.len() > LatestUpdate::LEN
+    {
+        return Err(ClientError::AccountDeserialize(
+            *address,
+            ErrorCode::AccountDiscriminatorMismatch.into(),
+        ));
+    }
+    let mut buffer = [0u8; LatestUpdate::LEN];
+    buffer[..data.len()].copy_from_slice(data);
+    Ok(bytemuck::pod_read_unaligned(&buffer[8..]))
+}
+
+pub async fn fetch_config(rpc_client: &RpcClient) -> Result<Config, ClientError> {
+    fetch_account(rpc_client, &pda::config().0).await
+}
+
+pub async fn fetch_treasury(rpc_client: &RpcClient) -> Result<Treasury, ClientError> {
+    fetch_account(rpc_client, &pda::treasury().0).await
+}
+
+pub async fn fetch_subscription(
+    rpc_client: &RpcClient,
+    subscriber: &Pubkey,
+) -> Result<Subscription, ClientError> {
+    fetch_account(rpc_client, &pda::subscription(subscriber).0).await
+}
+
+pub async fn fetch_protocol_info(
+    rpc_client: &RpcClient,
+    config: &Config,
+) -> Result<ProtocolInfo, ClientError> {
+    let address = pda::protocol_info(&config.protocol_id, &config.endpoint).0;
+    let account = fetch_raw_account(rpc_client, &address).await?;
+    ProtocolInfo::try_from_account(&account.owner, &account.data)
+        .map_err(|err| ClientError::AccountDeserialize(address, err))
+}
+
+pub async fn fetch_latest_update(
+    rpc_client: &RpcClient,
+    protocol_id: &[u8; 32],
+    data_key: &[u8; 32],
+) -> Result<LatestUpdate, ClientError> {
+    let address = pda::latest_update(protocol_id, data_key).0;
+    let account = fetch_raw_account(rpc_client, &address).await?;
+    decode_latest_update(&address, &account.data)
+}
+
+/// Fetches the latest updates of the data keys in as few requests as possible, the data keys
+/// that have never been published are returned as `None`
+pub async fn fetch_latest_updates(
+    rpc_client: &RpcClient,
+    protocol_id: &[u8; 32],
+    data_keys: &[[u8; 32]],
+) -> Result<Vec<Option<LatestUpdate>>, ClientError> {
+    let addresses: Vec<Pubkey> =
+        data_keys.iter().map(|data_key| pda::latest_update(protocol_id, data_key).0).collect();
+    let mut latest_updates = Vec::with_capacity(addresses.len());
+    for addresses in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc_client
+            .get_multiple_accounts_with_commitment(addresses, rpc_client.commitment())
+            .await?
+            .value;
+        for (address, account) in addresses.iter().zip(accounts) {
+            latest_updates.push(
+                account.map(|account| decode_latest_update(address, &account.data)).transpose()?,
+            );
+        }
+    }
+    Ok(latest_updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use udf_solana::test_utils::{bytes32, data_key};
+
+    fn latest_update() -> LatestUpdate {
+        LatestUpdate {
+            data_key: data_key("NGL/USD"),
+            data: bytes32("000000000000000000000000000000000000000000000000028268a1669ac38f"),
+            data_timestamp: 1721923139,
+            confidence: bytes32("0000000000000000000000000000000000000000000000000000000000000064"),
+            sources: 7,
+            paused: 1,
+            heartbeat: 60,
+            ..Default::default()
+        }
+    }
+
+    fn account_data(latest_update: &LatestUpdate) -> Vec<u8> {
+        [
+            &LatestUpdate::DISCRIMINATOR[..],
+            bytemuck::bytes_of(latest_update),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decodes_current_layout() {
+        let expected = latest_update();
+        let data = account_data(&expected);
+        assert_eq!(data.len(), LatestUpdate::LEN);
+
+        let decoded = decode_latest_update(&Pubkey::default(), &data).unwrap();
+        assert_eq!(decoded.data_key, expected.data_key);
+        assert_eq!(decoded.data, expected.data);
+        assert_eq!(decoded.data_timestamp, expected.data_timestamp);
+        assert_eq!(decoded.confidence, expected.confidence);
+        assert_eq!(decoded.sources, expected.sources);
+        assert_eq!(decoded.paused, expected.paused);
+        assert_eq!(decoded.heartbeat, expected.heartbeat);
+    }
+
+    #[test]
+    fn decodes_legacy_layout_with_zeroed_fields() {
+        // The accounts created before the confidence only hold the key, the data and the timestamp
+        let data = account_data(&latest_update())[..8 + 32 + 32 + 8].to_vec();
+
+        let decoded = decode_latest_update(&Pubkey::default(), &data).unwrap();
+        assert_eq!(decoded.data_key, latest_update().data_key);
+        assert_eq!(decoded.data_timestamp, latest_update().data_timestamp);
+        assert_eq!(decoded.confidence, [0u8; 32]);
+        assert_eq!(decoded.sources, 0);
+        assert_eq!(decoded.heartbeat, 0);
+    }
+
+    #[test]
+    fn rejects_other_accounts() {
+        let mut data = account_data(&latest_update());
+        data[0] ^= 1;
+        assert!(decode_latest_update(&Pubkey::default(), &data).is_err());
+        assert!(decode_latest_update(&Pubkey::default(), &data[..4]).is_err());
+
+        let mut data = account_data(&latest_update());
+        data.push(0);
+        assert!(decode_latest_update(&Pubkey::default(), &data).is_err());
+    }
+}
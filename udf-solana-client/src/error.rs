@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The rpc error is boxed to keep the results of the client small
+    #[error("Rpc error: {0}")]
+    Rpc(Box<solana_client::client_error::ClientError>),
+    #[error("Account not found: {0}")]
+    AccountNotFound(solana_sdk::pubkey::Pubkey),
+    #[error("Failed to deserialize account: {0}, error: {1}")]
+    AccountDeserialize(solana_sdk::pubkey::Pubkey, anchor_lang::error::Error),
+}
+
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(err: solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(Box::new(err))
+    }
+}
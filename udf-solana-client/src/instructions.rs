@@ -0,0 +1,530 @@
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use udf_solana::data::{
+    ConfidenceUpdateMessage, Config, DataFeed, DataKey, LastPriceMessage, LastPriceMessageV2,
+    MultiProofUpdateMessage, MultipleUpdateMessage, MultipleUpdateMessageV2, SignatureScheme,
+};
+
+use crate::pda;
+
+/// Builds the `update_multiple_assets` instruction with the latest update accounts of all the
+/// data feeds of the message passed as the remaining accounts
+pub fn update_multiple_assets(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    multiple_update_message: MultipleUpdateMessage,
+) -> Instruction {
+    let mut accounts = update_assets_accounts(publisher, protocol_id, endpoint);
+    accounts.extend(data_feed_accounts(protocol_id, &multiple_update_message.data_feeds));
+    let data = udf_solana::instruction::UpdateMultipleAssets {
+        multiple_update_message,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `update_multiple_assets_v2` instruction with the latest update accounts of all the
+/// data feeds of the message passed as the remaining accounts
+pub fn update_multiple_assets_v2(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    multiple_update_message: MultipleUpdateMessageV2,
+) -> Instruction {
+    let mut accounts = update_assets_accounts(publisher, protocol_id, endpoint);
+    accounts.extend(data_feed_accounts(protocol_id, &multiple_update_message.data_feeds));
+    let data = udf_solana::instruction::UpdateMultipleAssetsV2 {
+        multiple_update_message,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `update_multiple_assets_multiproof` instruction with the latest update accounts of
+/// all the data feeds of the message passed as the remaining accounts
+pub fn update_multiple_assets_multiproof(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    multi_proof_update_message: MultiProofUpdateMessage,
+) -> Instruction {
+    let mut accounts = update_assets_accounts(publisher, protocol_id, endpoint);
+    accounts.extend(multi_proof_update_message.data_feeds.iter().map(|data_feed| {
+        AccountMeta::new(pda::latest_update(protocol_id, &data_feed.data_key).0, false)
+    }));
+    let data = udf_solana::instruction::UpdateMultipleAssetsMultiproof {
+        multi_proof_update_message,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `update_multiple_assets_with_confidence` instruction with the latest update accounts
+/// of all the data feeds of the message passed as the remaining accounts
+pub fn update_multiple_assets_with_confidence(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    confidence_update_message: ConfidenceUpdateMessage,
+) -> Instruction {
+    let mut accounts = update_assets_accounts(publisher, protocol_id, endpoint);
+    accounts.extend(confidence_update_message.data_feeds.iter().map(|data_feed| {
+        AccountMeta::new(pda::latest_update(protocol_id, &data_feed.data_key).0, false)
+    }));
+    let data = udf_solana::instruction::UpdateMultipleAssetsWithConfidence {
+        confidence_update_message,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// The remaining accounts to append to the update instructions for the publisher to be reimbursed,
+/// the treasury is only passed while the config rewards this publisher
+pub fn reimbursement_accounts(config: &Config, publisher: &Pubkey) -> Vec<AccountMeta> {
+    let extension = &config.extension;
+    if extension.rewarded_publisher == *publisher
+        && (extension.update_reward > 0 || extension.reimbursement_cap > 0)
+    {
+        vec![AccountMeta::new(pda::treasury().0, false)]
+    } else {
+        vec![]
+    }
+}
+
+/// Builds the `get_last_price` instruction that verifies and stores the single data feed
+pub fn get_last_price(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    last_price_message: LastPriceMessage,
+) -> Instruction {
+    let mut accounts = update_assets_accounts(publisher, protocol_id, endpoint);
+    accounts.extend(data_feed_accounts(
+        protocol_id,
+        std::slice::from_ref(&last_price_message.data_feed),
+    ));
+    let data = udf_solana::instruction::GetLastPrice { last_price_message }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `get_last_price_v2` instruction, the same as `get_last_price` with fixed size
+/// signatures in the message
+pub fn get_last_price_v2(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    last_price_message: LastPriceMessageV2,
+) -> Instruction {
+    let mut accounts = update_assets_accounts(publisher, protocol_id, endpoint);
+    accounts.extend(data_feed_accounts(
+        protocol_id,
+        std::slice::from_ref(&last_price_message.data_feed),
+    ));
+    let data = udf_solana::instruction::GetLastPriceV2 { last_price_message }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `verify_price` instruction that verifies the single data feed without storing it
+pub fn verify_price(
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    last_price_message: LastPriceMessage,
+) -> Instruction {
+    let accounts = udf_solana::accounts::VerifyPrice {
+        config: pda::config().0,
+        protocol_info: pda::protocol_info(protocol_id, endpoint).0,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::VerifyPrice { last_price_message }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `verify_price_v2` instruction, the same as `verify_price` with fixed size
+/// signatures in the message
+pub fn verify_price_v2(
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+    last_price_message: LastPriceMessageV2,
+) -> Instruction {
+    let accounts = udf_solana::accounts::VerifyPrice {
+        config: pda::config().0,
+        protocol_info: pda::protocol_info(protocol_id, endpoint).0,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::VerifyPriceV2 { last_price_message }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// How the price reads cover the read fee of the config
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadFee {
+    /// The read fee is disabled, no payer is passed
+    #[default]
+    Free,
+    /// The payer is charged the read fee
+    Paid(Pubkey),
+    /// The subscriber reads with its subscription, falling back to paying the fee once it expires
+    Subscribed(Pubkey),
+}
+
+/// Builds the `last_price` instruction returning the stored price of the asset
+pub fn last_price(protocol_id: &[u8; 32], data_key: DataKey, read_fee: ReadFee) -> Instruction {
+    let accounts = last_price_accounts(protocol_id, &data_key, read_fee);
+    let data = udf_solana::instruction::LastPrice {
+        _data_key: data_key,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the admin instruction selecting the digest transmitters sign merkle roots with
+pub fn set_signature_scheme(
+    admin: &Pubkey,
+    signature_scheme: SignatureScheme,
+    chain_id: u128,
+) -> Instruction {
+    let accounts = udf_solana::accounts::UpdateConfig {
+        admin: *admin,
+        config: pda::config().0,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::SetSignatureScheme {
+        signature_scheme,
+        chain_id,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the admin instruction registering the rewarded publisher along with its reward per
+/// stored data feed and the cap of the reimbursement per instruction
+pub fn set_publisher_rewards(
+    admin: &Pubkey,
+    rewarded_publisher: &Pubkey,
+    update_reward: u64,
+    reimbursement_cap: u64,
+) -> Instruction {
+    let accounts = udf_solana::accounts::UpdateConfig {
+        admin: *admin,
+        config: pda::config().0,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::SetPublisherRewards {
+        rewarded_publisher: *rewarded_publisher,
+        update_reward,
+        reimbursement_cap,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the admin instruction reallocating the config created before its extension
+pub fn migrate_config(admin: &Pubkey) -> Instruction {
+    let accounts = udf_solana::accounts::MigrateConfig {
+        admin: *admin,
+        config: pda::config().0,
+        system_program: anchor_lang::system_program::ID,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::MigrateConfig {}.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the instruction transferring lamports to the treasury publishers are reimbursed from
+pub fn fund_treasury(funder: &Pubkey, amount: u64) -> Instruction {
+    let accounts = udf_solana::accounts::FundTreasury {
+        funder: *funder,
+        treasury: pda::treasury().0,
+        system_program: anchor_lang::system_program::ID,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::FundTreasury { amount }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `last_price_with_confidence` instruction returning the stored price of the asset
+/// along with its confidence and number of sources
+pub fn last_price_with_confidence(
+    protocol_id: &[u8; 32],
+    data_key: DataKey,
+    read_fee: ReadFee,
+) -> Instruction {
+    let accounts = last_price_accounts(protocol_id, &data_key, read_fee);
+    let data = udf_solana::instruction::LastPriceWithConfidence {
+        _data_key: data_key,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `last_price_v2` instruction returning the stored price of the asset along with the
+/// status of the feed
+pub fn last_price_v2(protocol_id: &[u8; 32], data_key: DataKey, read_fee: ReadFee) -> Instruction {
+    let accounts = last_price_accounts(protocol_id, &data_key, read_fee);
+    let data = udf_solana::instruction::LastPriceV2 {
+        _data_key: data_key,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `last_price_with_confidence_v2` instruction returning the stored price of the asset
+/// along with its confidence, number of sources and the status of the feed
+pub fn last_price_with_confidence_v2(
+    protocol_id: &[u8; 32],
+    data_key: DataKey,
+    read_fee: ReadFee,
+) -> Instruction {
+    let accounts = last_price_accounts(protocol_id, &data_key, read_fee);
+    let data = udf_solana::instruction::LastPriceWithConfidenceV2 {
+        _data_key: data_key,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the `migrate_latest_update` instruction reallocating the latest update account created
+/// before the zero copy layout, the payer covers the rent difference
+pub fn migrate_latest_update(
+    payer: &Pubkey,
+    protocol_id: &[u8; 32],
+    data_key: [u8; 32],
+) -> Instruction {
+    let accounts = udf_solana::accounts::MigrateLatestUpdate {
+        payer: *payer,
+        config: pda::config().0,
+        latest_update: pda::latest_update(protocol_id, &data_key).0,
+        system_program: anchor_lang::system_program::ID,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::MigrateLatestUpdate {
+        _data_key: data_key,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the admin instruction setting the expected heartbeat interval of the feed in seconds
+/// and pausing or resuming it
+pub fn set_feed_metadata(
+    admin: &Pubkey,
+    protocol_id: &[u8; 32],
+    data_key: [u8; 32],
+    heartbeat: u32,
+    paused: bool,
+) -> Instruction {
+    let accounts = udf_solana::accounts::UpdateFeedMetadata {
+        admin: *admin,
+        config: pda::config().0,
+        latest_update: pda::latest_update(protocol_id, &data_key).0,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::SetFeedMetadata {
+        _data_key: data_key,
+        heartbeat,
+        paused,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the admin instruction setting the fee charged for every price read and the daily fee of
+/// the subscriptions
+pub fn set_read_fees(admin: &Pubkey, read_fee: u64, subscription_daily_fee: u64) -> Instruction {
+    let accounts = udf_solana::accounts::UpdateConfig {
+        admin: *admin,
+        config: pda::config().0,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::SetReadFees {
+        read_fee,
+        subscription_daily_fee,
+    }
+    .data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the instruction extending the subscription of the subscriber by the given days
+pub fn subscribe(subscriber: &Pubkey, days: u32) -> Instruction {
+    let accounts = udf_solana::accounts::Subscribe {
+        subscriber: *subscriber,
+        config: pda::config().0,
+        subscription: pda::subscription(subscriber).0,
+        treasury: pda::treasury().0,
+        system_program: anchor_lang::system_program::ID,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::Subscribe { days }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+/// Builds the admin instruction withdrawing the accumulated fees from the treasury
+pub fn withdraw_treasury(admin: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    let accounts = udf_solana::accounts::WithdrawTreasury {
+        admin: *admin,
+        config: pda::config().0,
+        treasury: pda::treasury().0,
+        recipient: *recipient,
+    }
+    .to_account_metas(None);
+    let data = udf_solana::instruction::WithdrawTreasury { amount }.data();
+    Instruction::new_with_bytes(udf_solana::ID, &data, accounts)
+}
+
+pub fn last_price_accounts(
+    protocol_id: &[u8; 32],
+    data_key: &DataKey,
+    read_fee: ReadFee,
+) -> Vec<AccountMeta> {
+    let (payer, subscription) = match read_fee {
+        ReadFee::Free => (None, None),
+        ReadFee::Paid(payer) => (Some(payer), None),
+        ReadFee::Subscribed(subscriber) => {
+            (Some(subscriber), Some(pda::subscription(&subscriber).0))
+        }
+    };
+    udf_solana::accounts::LastPrice {
+        last_price: pda::latest_update(protocol_id, &data_key.key).0,
+        config: pda::config().0,
+        payer,
+        subscription,
+        treasury: payer.map(|_| pda::treasury().0),
+        system_program: payer.map(|_| anchor_lang::system_program::ID),
+    }
+    .to_account_metas(None)
+}
+
+pub fn update_assets_accounts(
+    publisher: &Pubkey,
+    protocol_id: &[u8; 32],
+    endpoint: &Pubkey,
+) -> Vec<AccountMeta> {
+    udf_solana::accounts::UpdateAssets {
+        publisher: *publisher,
+        config: pda::config().0,
+        protocol_info: pda::protocol_info(protocol_id, endpoint).0,
+        system_program: anchor_lang::system_program::ID,
+    }
+    .to_account_metas(None)
+}
+
+pub fn data_feed_accounts(protocol_id: &[u8; 32], data_feeds: &[DataFeed]) -> Vec<AccountMeta> {
+    data_feeds
+        .iter()
+        .map(|data_feed| {
+            AccountMeta::new(pda::latest_update(protocol_id, &data_feed.data_key).0, false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AnchorDeserialize, Discriminator};
+    use udf_solana::{test_utils::multiple_update_message, UDF_PROTOCOL_ID};
+
+    use crate::multi_proof;
+
+    fn protocol_id() -> [u8; 32] {
+        UDF_PROTOCOL_ID.try_into().unwrap()
+    }
+
+    fn latest_update_accounts(data_feeds: &[DataFeed]) -> Vec<Pubkey> {
+        data_feeds
+            .iter()
+            .map(|data_feed| pda::latest_update(&protocol_id(), &data_feed.data_key).0)
+            .collect()
+    }
+
+    #[test]
+    fn builds_update_with_latest_update_accounts() {
+        let publisher = Pubkey::new_unique();
+        let message = multiple_update_message();
+        let ix = update_multiple_assets_v2(
+            &publisher,
+            &protocol_id(),
+            &pda::PHOTON_PROGRAM,
+            message.clone(),
+        );
+
+        let base_accounts =
+            update_assets_accounts(&publisher, &protocol_id(), &pda::PHOTON_PROGRAM);
+        assert_eq!(ix.accounts[..base_accounts.len()], base_accounts[..]);
+        assert!(ix.accounts[0].is_signer && ix.accounts[0].pubkey == publisher);
+        let remaining = &ix.accounts[base_accounts.len()..];
+        assert_eq!(
+            remaining.iter().map(|meta| meta.pubkey).collect::<Vec<_>>(),
+            latest_update_accounts(&message.data_feeds)
+        );
+        assert!(remaining.iter().all(|meta| meta.is_writable && !meta.is_signer));
+
+        let discriminator = udf_solana::instruction::UpdateMultipleAssetsV2::DISCRIMINATOR;
+        assert_eq!(ix.data[..8], discriminator);
+        let decoded = MultipleUpdateMessageV2::try_from_slice(&ix.data[8..]).unwrap();
+        assert_eq!(decoded.merkle_root, message.merkle_root);
+        assert_eq!(decoded.data_feeds.len(), message.data_feeds.len());
+    }
+
+    #[test]
+    fn builds_multiproof_update_in_data_feed_order() {
+        let publisher = Pubkey::new_unique();
+        let message = multi_proof::from_update_message(&multiple_update_message()).unwrap();
+        let ix = update_multiple_assets_multiproof(
+            &publisher,
+            &protocol_id(),
+            &pda::PHOTON_PROGRAM,
+            message.clone(),
+        );
+
+        let remaining = &ix.accounts[ix.accounts.len() - message.data_feeds.len()..];
+        let expected: Vec<Pubkey> = message
+            .data_feeds
+            .iter()
+            .map(|leaf| pda::latest_update(&protocol_id(), &leaf.data_key).0)
+            .collect();
+        assert_eq!(remaining.iter().map(|meta| meta.pubkey).collect::<Vec<_>>(), expected);
+        let discriminator = udf_solana::instruction::UpdateMultipleAssetsMultiproof::DISCRIMINATOR;
+        assert_eq!(ix.data[..8], discriminator);
+    }
+
+    #[test]
+    fn passes_treasury_only_while_rewards_are_on() {
+        let publisher = Pubkey::new_unique();
+        let mut config = Config::default();
+        config.extension.update_reward = 1;
+        assert!(reimbursement_accounts(&config, &publisher).is_empty());
+
+        config.extension.rewarded_publisher = publisher;
+        let accounts = reimbursement_accounts(&config, &publisher);
+        assert_eq!(accounts, vec![AccountMeta::new(pda::treasury().0, false)]);
+        assert!(reimbursement_accounts(&config, &Pubkey::new_unique()).is_empty());
+
+        config.extension.update_reward = 0;
+        config.extension.reimbursement_cap = 1;
+        assert_eq!(reimbursement_accounts(&config, &publisher), accounts);
+        config.extension.reimbursement_cap = 0;
+        assert!(reimbursement_accounts(&config, &publisher).is_empty());
+    }
+
+    #[test]
+    fn passes_read_fee_accounts_of_the_payer() {
+        let data_key: DataKey = "NGL/USD".parse().unwrap();
+        let free = last_price_accounts(&protocol_id(), &data_key, ReadFee::Free);
+        assert!(free[2..].iter().all(|meta| meta.pubkey == udf_solana::ID));
+
+        let payer = Pubkey::new_unique();
+        let paid = last_price_accounts(&protocol_id(), &data_key, ReadFee::Paid(payer));
+        assert_eq!(paid[2], AccountMeta::new(payer, true));
+        assert_eq!(paid[3].pubkey, udf_solana::ID);
+        assert_eq!(paid[4], AccountMeta::new(pda::treasury().0, false));
+        assert_eq!(paid[5].pubkey, anchor_lang::system_program::ID);
+
+        let subscribed = last_price_accounts(&protocol_id(), &data_key, ReadFee::Subscribed(payer));
+        assert_eq!(subscribed[3].pubkey, pda::subscription(&payer).0);
+        assert_eq!(subscribed[4..], paid[4..]);
+    }
+}
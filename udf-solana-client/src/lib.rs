@@ -0,0 +1,21 @@
+//! Client SDK of the UDF solana price oracle: PDA derivation, instruction builders, off-chain
+//! verification of data feeds, signing digests and typed account fetchers.
+
+pub mod accounts;
+mod error;
+pub mod instructions;
+pub mod multi_proof;
+pub mod pda;
+pub mod signing;
+pub mod verify;
+
+pub use error::ClientError;
+pub use udf_solana::{
+    data::{
+        ConfidenceDataFeed, ConfidenceUpdateMessage, Config, ConfigExtension, DataFeed,
+        DataFeedLeaf, DataKey, FeedStatus, LastPriceMessage, LastPriceMessageV2, LatestUpdate,
+        MultiProofUpdateMessage, MultipleUpdateMessage, MultipleUpdateMessageV2, SignatureScheme,
+        Subscription, TransmitterSignature, TransmitterSignatureV2, Treasury, UpdateStatus,
+    },
+    ID as UDF_SOLANA_PROGRAM,
+};
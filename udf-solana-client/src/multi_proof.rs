@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use udf_solana::{
+    data::{DataFeedLeaf, MultiProofUpdateMessage, MultipleUpdateMessageV2},
+    hash::{self, Hash},
+};
+
+use crate::verify::{self, data_feed_leaf};
+
+/// Combines the merkle proofs of the data feeds of the message into a single deduplicated proof,
+/// in which every node shared by the proofs is carried once. `None` when the proofs don't lead
+/// to the merkle root or can't be ordered for the OpenZeppelin `multiProofVerify` algorithm
+pub fn from_update_message(message: &MultipleUpdateMessageV2) -> Option<MultiProofUpdateMessage> {
+    let mut siblings: HashMap<Hash, Hash> = HashMap::new();
+    let mut parents: HashMap<Hash, Hash> = HashMap::new();
+    // The path of every data feed from the root down to its leaf
+    let mut paths = Vec::with_capacity(message.data_feeds.len());
+    for data_feed in &message.data_feeds {
+        let mut node = data_feed_leaf(data_feed.timestamp, &data_feed.data_key, &data_feed.data);
+        let mut path = vec![node];
+        for sibling in &data_feed.merkle_proof {
+            let parent = hash::commutative_keccak256(node, *sibling);
+            siblings.insert(node, *sibling);
+            parents.insert(node, parent);
+            node = parent;
+            path.push(node);
+        }
+        if node != message.merkle_root {
+            return None;
+        }
+        path.reverse();
+        paths.push((path, data_feed));
+    }
+    // The deepest leaves go first and the leaves of the same subtree go next to each other on
+    // every level, so that the siblings known to the verifier are adjacent in its queue
+    paths.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    let known: HashSet<Hash> = paths.iter().flat_map(|(path, _)| path.iter().copied()).collect();
+
+    let mut queue: VecDeque<Hash> =
+        paths.iter().filter_map(|(path, _)| path.last()).copied().collect();
+    let (mut proof, mut proof_flags) = (vec![], vec![]);
+    while let Some(node) = queue.pop_front() {
+        if node == message.merkle_root {
+            if !queue.is_empty() {
+                return None;
+            }
+            break;
+        }
+        let sibling = siblings[&node];
+        if queue.front() == Some(&sibling) {
+            queue.pop_front();
+            proof_flags.push(true);
+        } else if known.contains(&sibling) {
+            return None;
+        } else {
+            proof_flags.push(false);
+            proof.push(sibling);
+        }
+        queue.push_back(parents[&node]);
+    }
+
+    let message = MultiProofUpdateMessage {
+        merkle_root: message.merkle_root,
+        data_feeds: paths
+            .into_iter()
+            .map(|(_, data_feed)| DataFeedLeaf {
+                timestamp: data_feed.timestamp,
+                data_key: data_feed.data_key,
+                data: data_feed.data,
+            })
+            .collect(),
+        proof,
+        proof_flags,
+        signatures: message.signatures.clone(),
+    };
+    verify::verify_multi_proof(&message).then_some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use udf_solana::test_utils::multiple_update_message as message;
+
+    fn subset(indices: &[usize]) -> MultipleUpdateMessageV2 {
+        let mut message = message();
+        message.data_feeds = indices.iter().map(|i| message.data_feeds[*i].clone()).collect();
+        message
+    }
+
+    #[test]
+    fn combines_proofs_of_all_data_feeds() {
+        let multi_proof = from_update_message(&message()).unwrap();
+        assert_eq!(multi_proof.data_feeds.len(), 3);
+        // NGL and BTC are siblings, only the other node of the ETH subtree is left to prove
+        assert_eq!(multi_proof.proof.len(), 1);
+        assert_eq!(multi_proof.proof_flags.len(), 3);
+    }
+
+    #[test]
+    fn combines_proofs_of_any_data_feeds() {
+        for indices in [&[0, 1][..], &[1, 2], &[2, 0], &[2, 1, 0], &[1]] {
+            let multi_proof = from_update_message(&subset(indices)).unwrap();
+            assert_eq!(multi_proof.data_feeds.len(), indices.len());
+        }
+    }
+
+    #[test]
+    fn rejects_proof_of_other_root() {
+        let mut message = message();
+        message.data_feeds[1].merkle_proof[0][0] ^= 1;
+        assert!(from_update_message(&message).is_none());
+    }
+
+    #[test]
+    fn rejects_duplicate_data_feeds() {
+        assert!(from_update_message(&subset(&[0, 0])).is_none());
+    }
+}
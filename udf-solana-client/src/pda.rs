@@ -0,0 +1,57 @@
+use solana_sdk::pubkey::Pubkey;
+use udf_solana::udf_solana::{PHOTON_ROOT, ROOT};
+
+pub use udf_solana::photon::PHOTON_PROGRAM;
+
+pub fn config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROOT, b"CONFIG"], &udf_solana::ID)
+}
+
+/// Derives the treasury publishers are reimbursed from
+pub fn treasury() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROOT, b"TREASURY"], &udf_solana::ID)
+}
+
+pub fn subscription(subscriber: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROOT, b"SUBSCRIPTION", subscriber.as_ref()], &udf_solana::ID)
+}
+
+/// Derives the protocol info account, the `endpoint` is the photon program stored in the config
+pub fn protocol_info(protocol_id: &[u8; 32], endpoint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PHOTON_ROOT, b"PROTOCOL", protocol_id], endpoint)
+}
+
+/// Derives the latest update account of the data key, the same key the data feeds carry
+pub fn latest_update(protocol_id: &[u8; 32], data_key: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ROOT, b"LAST_UPDATE", protocol_id, data_key], &udf_solana::ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use udf_solana::{test_utils::data_key, UDF_PROTOCOL_ID};
+
+    fn protocol_id() -> [u8; 32] {
+        UDF_PROTOCOL_ID.try_into().unwrap()
+    }
+
+    // The addresses are the ones of the test deployment, they change with the program id or seeds
+    #[test]
+    fn derives_program_accounts() {
+        assert_eq!(config().0.to_string(), "2r6evAGjhh5htsfHZP4hMLv6Ppa1KeY9K34Nz3nAMs5u");
+        assert_eq!(treasury().0.to_string(), "DAqaHHzyYneYsS8ZfFL1khwGPhefvC3cLnAcDhXkkAzR");
+    }
+
+    #[test]
+    fn derives_latest_update_of_data_key() {
+        let (address, _) = latest_update(&protocol_id(), &data_key("NGL/USD"));
+        assert_eq!(address.to_string(), "9RD6d4hnShVGP3DA73DviceLe8JLp53BB3rBZeuFnWKy");
+        assert_ne!(latest_update(&protocol_id(), &data_key("BTC/USD")).0, address);
+    }
+
+    #[test]
+    fn derives_protocol_info_of_endpoint() {
+        let (address, _) = protocol_info(&protocol_id(), &PHOTON_PROGRAM);
+        assert_eq!(address.to_string(), "3skPHvBJyYRKnJ9MvyD4VJVJRjai3oatCUZEzKzKAzY3");
+    }
+}
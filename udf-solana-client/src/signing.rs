@@ -0,0 +1,69 @@
+use udf_solana::{data::Config, hash::Hash};
+
+pub use udf_solana::signing::{
+    domain_separated_digest, domain_separator, eth_signed_message_digest, SIGNING_DOMAIN_VERSION,
+};
+
+/// Calculates the digest transmitters are expected to sign for the scheme selected in the config
+pub fn merkle_root_digest(config: &Config, merkle_root: &Hash) -> Hash {
+    udf_solana::signing::merkle_root_digest(config, &udf_solana::ID, merkle_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::secp256k1_recover::secp256k1_recover;
+    use udf_solana::{
+        data::SignatureScheme,
+        hash::keccak256,
+        test_utils::{
+            bytes32, single_update_signature, SINGLE_UPDATE_MERKLE_ROOT, SINGLE_UPDATE_TRANSMITTER,
+        },
+    };
+
+    fn config(signature_scheme: SignatureScheme, chain_id: u128) -> Config {
+        Config {
+            protocol_id: udf_solana::UDF_PROTOCOL_ID.try_into().unwrap(),
+            signature_scheme,
+            chain_id,
+            ..Default::default()
+        }
+    }
+
+    /// Recovers the eth address of the signer of the digest
+    fn recover(digest: &Hash) -> String {
+        let signature = single_update_signature();
+        let public_key = secp256k1_recover(
+            digest,
+            signature.v - 27,
+            &[&signature.r[..], &signature.s[..]].concat(),
+        )
+        .unwrap();
+        hex::encode(&keccak256(public_key.0)[12..])
+    }
+
+    #[test]
+    fn recorded_signature_signs_eth_signed_message_digest() {
+        let merkle_root = bytes32(SINGLE_UPDATE_MERKLE_ROOT);
+        let digest =
+            merkle_root_digest(&config(SignatureScheme::EthSignedMessage, 0), &merkle_root);
+        assert_eq!(digest, eth_signed_message_digest(&merkle_root));
+        assert_eq!(recover(&digest), SINGLE_UPDATE_TRANSMITTER);
+    }
+
+    #[test]
+    fn domain_separated_digest_is_bound_to_chain_and_program() {
+        let merkle_root = bytes32(SINGLE_UPDATE_MERKLE_ROOT);
+        let config = config(SignatureScheme::DomainSeparated, 1);
+        let digest = merkle_root_digest(&config, &merkle_root);
+        let domain = domain_separator(&config.protocol_id, 1, &udf_solana::ID);
+        assert_eq!(digest, domain_separated_digest(&domain, &merkle_root));
+
+        // The recorded signature is only valid for the eth signed message digest
+        assert_ne!(recover(&digest), SINGLE_UPDATE_TRANSMITTER);
+        let other_chain = domain_separator(&config.protocol_id, 2, &udf_solana::ID);
+        assert_ne!(digest, domain_separated_digest(&other_chain, &merkle_root));
+        let other_program = domain_separator(&config.protocol_id, 1, &Default::default());
+        assert_ne!(digest, domain_separated_digest(&other_program, &merkle_root));
+    }
+}
@@ -0,0 +1,66 @@
+use udf_solana::{
+    data::{ConfidenceDataFeed, DataFeed, MultiProofUpdateMessage},
+    hash::{self, Hash},
+};
+
+/// Calculates the merkle leaf of the data feed in the same way the price oracle does
+pub fn data_feed_leaf(timestamp: u64, data_key: &[u8; 32], data: &[u8; 32]) -> Hash {
+    udf_solana::data_feed_leaf(timestamp, data_key, data)
+}
+
+/// Checks the merkle proof of the data feed against the given root
+pub fn verify_data_feed(data_feed: &DataFeed, merkle_root: &Hash) -> bool {
+    udf_solana::verify_data_feed(data_feed, *merkle_root)
+}
+
+/// Checks the merkle proof of the data feed with confidence against the given root
+pub fn verify_confidence_data_feed(data_feed: &ConfidenceDataFeed, merkle_root: &Hash) -> bool {
+    udf_solana::verify_confidence_data_feed(data_feed, *merkle_root)
+}
+
+/// Checks the deduplicated proof of all the data feeds in the multiproof message
+pub fn verify_multi_proof(message: &MultiProofUpdateMessage) -> bool {
+    let leaves: Vec<Hash> = message
+        .data_feeds
+        .iter()
+        .map(|leaf| data_feed_leaf(leaf.timestamp, &leaf.data_key, &leaf.data))
+        .collect();
+    hash::multi_proof_verify(&message.proof, &message.proof_flags, message.merkle_root, &leaves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use udf_solana::test_utils::multiple_update_message;
+
+    use crate::multi_proof;
+
+    #[test]
+    fn verifies_recorded_data_feeds() {
+        let message = multiple_update_message();
+        for data_feed in &message.data_feeds {
+            assert!(verify_data_feed(data_feed, &message.merkle_root));
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_data_feed() {
+        let message = multiple_update_message();
+        let mut data_feed = message.data_feeds[0].clone();
+        data_feed.data[31] ^= 1;
+        assert!(!verify_data_feed(&data_feed, &message.merkle_root));
+
+        let mut data_feed = message.data_feeds[0].clone();
+        data_feed.timestamp += 1;
+        assert!(!verify_data_feed(&data_feed, &message.merkle_root));
+        assert!(!verify_data_feed(&message.data_feeds[0], &[0u8; 32]));
+    }
+
+    #[test]
+    fn verifies_multi_proof_of_recorded_data_feeds() {
+        let mut message = multi_proof::from_update_message(&multiple_update_message()).unwrap();
+        assert!(verify_multi_proof(&message));
+        message.data_feeds[0].data[31] ^= 1;
+        assert!(!verify_multi_proof(&message));
+    }
+}
@@ -0,0 +1,117 @@
+//! Typed wrappers of the price oracle instructions to be called through CPI
+
+use anchor_lang::{prelude::*, solana_program::program::get_return_data};
+use udf_solana::data::{DataKey, FeedStatus, LastPriceMessage, LastPriceMessageV2};
+
+pub use udf_solana::cpi::accounts::{
+    LastPrice as LastPriceAccounts, UpdateAssets as UpdateAssetsAccounts,
+    VerifyPrice as VerifyPriceAccounts,
+};
+
+use crate::Price;
+
+/// Verifies the signed price by the price oracle and stores it in the latest update account,
+/// which is expected to be passed as the only remaining account of the context.
+pub fn get_last_price<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, UpdateAssetsAccounts<'info>>,
+    last_price_message: LastPriceMessage,
+) -> Result<Price> {
+    let data_key = last_price_message.data_feed.data_key;
+    let timestamp = last_price_message.data_feed.timestamp;
+    let data = udf_solana::cpi::get_last_price(ctx, last_price_message)?.get();
+    Ok(Price {
+        data_key,
+        data,
+        timestamp,
+        ..Default::default()
+    })
+}
+
+/// The same as `get_last_price` with fixed size signatures in the message
+pub fn get_last_price_v2<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, UpdateAssetsAccounts<'info>>,
+    last_price_message: LastPriceMessageV2,
+) -> Result<Price> {
+    let data_key = last_price_message.data_feed.data_key;
+    let timestamp = last_price_message.data_feed.timestamp;
+    let data = udf_solana::cpi::get_last_price_v2(ctx, last_price_message)?.get();
+    Ok(Price {
+        data_key,
+        data,
+        timestamp,
+        ..Default::default()
+    })
+}
+
+/// Verifies the signed price by the price oracle without storing it, no latest update account is
+/// needed
+pub fn verify_price<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, VerifyPriceAccounts<'info>>,
+    last_price_message: LastPriceMessage,
+) -> Result<Price> {
+    let data_key = last_price_message.data_feed.data_key;
+    udf_solana::cpi::verify_price(ctx, last_price_message)?;
+    let (_, return_data) = get_return_data().ok_or(ErrorCode::InstructionDidNotDeserialize)?;
+    let (data, timestamp) = <([u8; 32], u64)>::try_from_slice(&return_data)
+        .map_err(|_| ErrorCode::InstructionDidNotDeserialize)?;
+    Ok(Price {
+        data_key,
+        data,
+        timestamp,
+        ..Default::default()
+    })
+}
+
+/// The same as `verify_price` with fixed size signatures in the message
+pub fn verify_price_v2<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, VerifyPriceAccounts<'info>>,
+    last_price_message: LastPriceMessageV2,
+) -> Result<Price> {
+    let data_key = last_price_message.data_feed.data_key;
+    udf_solana::cpi::verify_price_v2(ctx, last_price_message)?;
+    let (_, return_data) = get_return_data().ok_or(ErrorCode::InstructionDidNotDeserialize)?;
+    let (data, timestamp) = <([u8; 32], u64)>::try_from_slice(&return_data)
+        .map_err(|_| ErrorCode::InstructionDidNotDeserialize)?;
+    Ok(Price {
+        data_key,
+        data,
+        timestamp,
+        ..Default::default()
+    })
+}
+
+/// Gets the price stored in the latest update account by the price oracle. When the read fee is
+/// enabled, the payer of the accounts is charged the fee unless its subscription is passed
+pub fn last_price<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, LastPriceAccounts<'info>>,
+    data_key: DataKey,
+) -> Result<Price> {
+    udf_solana::cpi::last_price(ctx, data_key)?;
+    let (_, return_data) = get_return_data().ok_or(ErrorCode::InstructionDidNotDeserialize)?;
+    let (data, timestamp) = <([u8; 32], u64)>::try_from_slice(&return_data)
+        .map_err(|_| ErrorCode::InstructionDidNotDeserialize)?;
+    Ok(Price {
+        data_key: data_key.key,
+        data,
+        timestamp,
+        ..Default::default()
+    })
+}
+
+/// The same as `last_price` along with the status of the feed
+pub fn last_price_v2<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, LastPriceAccounts<'info>>,
+    data_key: DataKey,
+) -> Result<Price> {
+    udf_solana::cpi::last_price_v2(ctx, data_key)?;
+    let (_, return_data) = get_return_data().ok_or(ErrorCode::InstructionDidNotDeserialize)?;
+    let (data, timestamp, status) = <([u8; 32], u64, FeedStatus)>::try_from_slice(&return_data)
+        .map_err(|_| ErrorCode::InstructionDidNotDeserialize)?;
+    Ok(Price {
+        data_key: data_key.key,
+        data,
+        timestamp,
+        status,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,13 @@
+use anchor_lang::error_code;
+
+#[error_code]
+pub enum ConsumerError {
+    #[msg("Latest update account is not owned by the price oracle")]
+    InvalidOwner,
+    #[msg("Latest update account address doesn't match its data key")]
+    InvalidAddress,
+    #[msg("Latest update account has the layout before zero copy and has to be migrated")]
+    AccountNotMigrated,
+    #[msg("Price is older than the max age")]
+    StalePrice,
+}
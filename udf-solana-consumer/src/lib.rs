@@ -0,0 +1,10 @@
+//! Helpers for programs consuming the UDF solana prices, either by reading the latest update
+//! accounts directly or through the typed CPI calls of the price oracle.
+
+pub mod cpi;
+mod error;
+mod price;
+
+pub use error::ConsumerError;
+pub use price::{read_price, Price};
+pub use udf_solana::{data, ID as UDF_SOLANA_PROGRAM};
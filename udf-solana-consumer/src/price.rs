@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use udf_solana::{
+    data::{FeedStatus, LatestUpdate},
+    udf_solana::ROOT,
+    UDF_PROTOCOL_ID,
+};
+
+use crate::ConsumerError;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Price {
+    pub data_key: [u8; 32],
+    pub data: [u8; 32],
+    pub timestamp: u64,
+    /// The spread between sources, zero when it isn't provided with the update
+    pub confidence: [u8; 32],
+    /// The number of sources the price is aggregated from, zero when it isn't provided
+    pub sources: u16,
+    /// Whether the feed is updated within its heartbeat interval or paused
+    pub status: FeedStatus,
+}
+
+impl Price {
+    /// The price value, which is stored as a big endian number in the lower 16 bytes of data
+    pub fn value(&self) -> u128 {
+        let mut value = [0u8; 16];
+        value.copy_from_slice(&self.data[16..]);
+        u128::from_be_bytes(value)
+    }
+}
+
+/// Reads the price from the latest update account without calling the price oracle. The account
+/// is checked to be owned by the price oracle and to be derived from the data key it stores, so
+/// the caller is only expected to compare `data_key` of the price with the one it is interested in.
+/// The account bytes are cast in place, accounts created before the current layout have to be
+/// migrated first.
+pub fn read_price<'info>(account_info: &'info AccountInfo<'info>, max_age: u64) -> Result<Price> {
+    require_keys_eq!(*account_info.owner, udf_solana::ID, ConsumerError::InvalidOwner);
+    require!(account_info.data_len() >= LatestUpdate::LEN, ConsumerError::AccountNotMigrated);
+    let latest_update_loader = AccountLoader::<LatestUpdate>::try_from(account_info)?;
+    let latest_update = latest_update_loader.load()?;
+
+    let (latest_update_pda, _) = Pubkey::find_program_address(
+        &[
+            ROOT,
+            b"LAST_UPDATE",
+            UDF_PROTOCOL_ID,
+            &latest_update.data_key,
+        ],
+        &udf_solana::ID,
+    );
+    require_keys_eq!(latest_update_pda, *account_info.key, ConsumerError::InvalidAddress);
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        (now.max(0) as u64).saturating_sub(latest_update.data_timestamp) <= max_age,
+        ConsumerError::StalePrice
+    );
+
+    Ok(Price {
+        data_key: latest_update.data_key,
+        data: latest_update.data,
+        timestamp: latest_update.data_timestamp,
+        confidence: latest_update.confidence,
+        sources: latest_update.sources,
+        status: latest_update.status(now),
+    })
+}